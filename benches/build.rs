@@ -1,4 +1,4 @@
-use ploc_bvh::prelude::BvhAabb3d;
+use ploc_bvh::{prelude::BvhAabb3d, SpaceFillingCurve};
 
 use std::time::{Duration, Instant};
 
@@ -51,9 +51,30 @@ fn build(c: &mut Criterion) {
     });
 }
 
+fn build_hilbert(c: &mut Criterion) {
+    c.bench_function("many boxes (hilbert)", |b| {
+        b.iter_custom(|iter| {
+            let boxes = generate_boxes();
+            let mut elapsed = Duration::ZERO;
+            for _ in 0..iter {
+                let start = Instant::now();
+                let bvh = BvhAabb3d::new_with_curve(
+                    boxes.len(),
+                    boxes.iter().enumerate().map(|(i, aabb)| (i as u32, *aabb)),
+                    SpaceFillingCurve::Hilbert,
+                );
+                elapsed += start.elapsed();
+                assert_eq!(bvh.n_items(), N_BOXES);
+            }
+
+            elapsed
+        })
+    });
+}
+
 criterion_group! {
     name = benches;
     config = Criterion::default().sample_size(50);
-    targets = build
+    targets = build, build_hilbert
 }
 criterion_main!(benches);
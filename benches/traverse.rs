@@ -0,0 +1,135 @@
+use ploc_bvh::prelude::BvhAabb3d;
+use ploc_bvh::wide::{Bvh4, Bvh8};
+
+use bevy_math::{
+    bounding::{Aabb3d, RayCast3d},
+    Dir3A, Vec3A,
+};
+use criterion::{criterion_group, criterion_main, Criterion};
+
+const N_BOXES: usize = 100_000;
+const N_QUERIES: usize = 1000;
+
+fn random_vec() -> Vec3A {
+    Vec3A::new(fastrand::f32(), fastrand::f32(), fastrand::f32())
+}
+
+fn generate_bvh() -> BvhAabb3d<u32> {
+    fastrand::seed(1);
+
+    let boxes = (0..N_BOXES).map(|i| {
+        let pos = random_vec() * 1000. - 500.;
+        let half_size = random_vec() * 4. + 1.;
+        (i as u32, Aabb3d::new(pos, half_size))
+    });
+    BvhAabb3d::new(N_BOXES, boxes)
+}
+
+fn traverse(c: &mut Criterion) {
+    let mut bvh = generate_bvh();
+    let mut stack = bvh.create_stack();
+
+    let queries = (0..N_QUERIES)
+        .map(|_| Aabb3d::new(random_vec() * 1000. - 500., Vec3A::splat(20.)))
+        .collect::<Vec<_>>();
+    c.bench_function("aabb queries", |b| {
+        b.iter(|| {
+            queries
+                .iter()
+                .map(|query| bvh.traverse(&mut stack, *query).count())
+                .sum::<usize>()
+        })
+    });
+
+    let rays = (0..N_QUERIES)
+        .map(|_| {
+            let direction = Dir3A::new(random_vec() - 0.5).unwrap();
+            RayCast3d::new(random_vec() * 1000. - 500., direction, 500.)
+        })
+        .collect::<Vec<_>>();
+    c.bench_function("ray casts", |b| {
+        b.iter(|| {
+            rays.iter()
+                .map(|ray| bvh.traverse(&mut stack, ray.clone()).count())
+                .sum::<usize>()
+        })
+    });
+
+    c.bench_function("stackless aabb queries (parent links)", |b| {
+        b.iter(|| {
+            queries
+                .iter()
+                .map(|query| bvh.traverse_stackless(*query).count())
+                .sum::<usize>()
+        })
+    });
+
+    bvh.compute_skip_links();
+    c.bench_function("stackless aabb queries", |b| {
+        b.iter(|| {
+            queries
+                .iter()
+                .map(|query| bvh.traverse_stackless(*query).count())
+                .sum::<usize>()
+        })
+    });
+
+    c.bench_function("simd aabb queries", |b| {
+        b.iter(|| {
+            queries
+                .iter()
+                .map(|query| bvh.traverse_aabb(&mut stack, *query).count())
+                .sum::<usize>()
+        })
+    });
+    c.bench_function("simd ray casts", |b| {
+        b.iter(|| {
+            rays.iter()
+                .map(|ray| bvh.traverse_ray(&mut stack, ray.clone()).count())
+                .sum::<usize>()
+        })
+    });
+
+    let bvh4 = Bvh4::new(generate_bvh());
+    let mut stack = bvh4.create_stack();
+    c.bench_function("bvh4 aabb queries", |b| {
+        b.iter(|| {
+            queries
+                .iter()
+                .map(|query| bvh4.traverse_lanes(&mut stack, *query).count())
+                .sum::<usize>()
+        })
+    });
+    c.bench_function("bvh4 ray casts", |b| {
+        b.iter(|| {
+            rays.iter()
+                .map(|ray| bvh4.traverse_lanes(&mut stack, ray.clone()).count())
+                .sum::<usize>()
+        })
+    });
+
+    let bvh8 = Bvh8::new(generate_bvh());
+    let mut stack = bvh8.create_stack();
+    c.bench_function("bvh8 aabb queries", |b| {
+        b.iter(|| {
+            queries
+                .iter()
+                .map(|query| bvh8.traverse_lanes(&mut stack, *query).count())
+                .sum::<usize>()
+        })
+    });
+    c.bench_function("bvh8 ray casts", |b| {
+        b.iter(|| {
+            rays.iter()
+                .map(|ray| bvh8.traverse_lanes(&mut stack, ray.clone()).count())
+                .sum::<usize>()
+        })
+    });
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(50);
+    targets = traverse
+}
+criterion_main!(benches);
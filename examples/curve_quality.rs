@@ -0,0 +1,40 @@
+//! Compares the tree quality of the supported space-filling curves, using the benchmark data
+
+use ploc_bvh::{prelude::BvhAabb3d, SpaceFillingCurve};
+
+use bevy_math::{bounding::Aabb3d, Vec3};
+
+/// The boxes used by the build benchmark, scaled to cover a larger area
+fn generate_boxes(n: usize, extent: f32) -> Vec<Aabb3d> {
+    fastrand::seed(1);
+
+    (0..n)
+        .map(|_| {
+            let pos =
+                Vec3::new(fastrand::f32(), fastrand::f32(), fastrand::f32()) * extent - extent / 2.;
+            let half_size = Vec3::new(fastrand::f32(), fastrand::f32(), fastrand::f32()) * 4. + 1.;
+            Aabb3d::new(pos, half_size)
+        })
+        .collect()
+}
+
+fn main() {
+    println!(
+        "{:>8} {:>8} {:>10} {:>10}",
+        "boxes", "extent", "morton", "hilbert"
+    );
+    for (n, extent) in [
+        (1000, 50.),
+        (10_000, 500.),
+        (100_000, 2000.),
+        (100_000, 20_000.),
+    ] {
+        let boxes = generate_boxes(n, extent);
+        let [morton, hilbert] =
+            [SpaceFillingCurve::Morton, SpaceFillingCurve::Hilbert].map(|curve| {
+                BvhAabb3d::new_with_curve(boxes.len(), boxes.iter().copied().enumerate(), curve)
+                    .sah_cost()
+            });
+        println!("{n:>8} {extent:>8} {morton:>10.2} {hilbert:>10.2}");
+    }
+}
@@ -1,13 +1,22 @@
 use crate::search::{find_best_node, FindCache};
-use crate::{Bvh, BvhItem, BvhNode, BvhVolume};
+use crate::{ceil_log2, Bvh, BvhItem, BvhItemVolume, BvhNode, BvhVolume, SpaceFillingCurve};
 
-use std::collections::VecDeque;
+use alloc::{collections::VecDeque, vec, vec::Vec};
 
 const TRAVERSE_COST: f32 = 1.5;
 
-impl<Volume: BvhVolume, T: Copy + std::fmt::Debug> Bvh<Volume, T> {
+impl<Volume: BvhVolume, T, Item: BvhItemVolume<Volume>> Bvh<Volume, T, Item> {
     /// Construct a BVH from a size and iterator
-    pub fn new(max_items: usize, iter: impl IntoIterator<Item = (T, impl Into<Volume>)>) -> Self {
+    pub fn new(max_items: usize, iter: impl IntoIterator<Item = (T, impl Into<Item>)>) -> Self {
+        Self::new_with_curve(max_items, iter, SpaceFillingCurve::Morton)
+    }
+
+    /// Construct a BVH from a size and iterator, ordering the items along the provided curve
+    pub fn new_with_curve(
+        max_items: usize,
+        iter: impl IntoIterator<Item = (T, impl Into<Item>)>,
+        curve: SpaceFillingCurve,
+    ) -> Self {
         if max_items == 0 {
             return Self::default();
         }
@@ -15,20 +24,33 @@ impl<Volume: BvhVolume, T: Copy + std::fmt::Debug> Bvh<Volume, T> {
         let mut current_nodes = Vec::with_capacity(max_items);
         let mut items = Vec::with_capacity(max_items);
 
-        for (i, (t, volume)) in iter.into_iter().enumerate() {
-            let volume = volume.into();
+        let mut bounds: Option<Volume> = None;
+        for (i, (t, item)) in iter.into_iter().enumerate() {
+            let item: Item = item.into();
+            let volume = item.node_volume();
+            bounds = Some(match bounds {
+                Some(bounds) => bounds.merge(&volume),
+                None => volume.clone(),
+            });
             current_nodes.push((
                 BvhNode {
-                    volume: volume.clone(),
+                    volume,
                     count: 1,
                     start_index: i as u32,
                 },
-                volume.morton_code(),
+                0,
             ));
-            items.push(BvhItem { volume, t });
+            items.push(BvhItem { volume: item, t });
         }
         let n_items = items.len();
 
+        // Codes are only computed once the bounds of all volumes are known
+        if let Some(bounds) = bounds {
+            for (node, code) in &mut current_nodes {
+                *code = node.volume.curve_code(curve, &bounds);
+            }
+        }
+
         radsort::sort_by_key(&mut current_nodes, |(_, code)| *code);
         let mut current_nodes = current_nodes
             .drain(..)
@@ -90,10 +112,10 @@ impl<Volume: BvhVolume, T: Copy + std::fmt::Debug> Bvh<Volume, T> {
 
         debug_assert_eq!(insert_index, 0);
 
-        // Order the list of items to match the nodes
-        let unordered_items = items;
+        // Order the list of items to match the nodes, moving each one into its final slot
+        let mut unordered_items = items.into_iter().map(Some).collect::<Vec<_>>();
         let mut items = Vec::with_capacity(n_items);
-        let mut stack = VecDeque::with_capacity((n_items as f32).log2().ceil() as usize + 10);
+        let mut stack = VecDeque::with_capacity(ceil_log2(n_items) + 10);
         stack.push_back(0u32);
         while let Some(index) = stack.pop_front() {
             let node = &mut nodes[index as usize];
@@ -103,7 +125,8 @@ impl<Volume: BvhVolume, T: Copy + std::fmt::Debug> Bvh<Volume, T> {
                 continue;
             }
 
-            items.push(unordered_items[node.start_index as usize].clone());
+            let item = unordered_items[node.start_index as usize].take();
+            items.push(item.expect("Each item is reached once"));
             node.start_index = items.len() as u32 - 1;
         }
 
@@ -160,16 +183,66 @@ impl<Volume: BvhVolume, T: Copy + std::fmt::Debug> Bvh<Volume, T> {
             }
         }
 
-        // TODO: Clean up dead nodes (count = 0, start_index = 0)
+        // Lay the nodes out depth-first, so each pair of children directly follows the pair
+        // containing their parent. This also drops the dead nodes left behind by merging
+        let mut ordered = Vec::with_capacity(nodes.len());
+        let mut parents = Vec::with_capacity(nodes.len());
+        ordered.push(nodes[0].clone());
+        parents.push(u32::MAX);
+        let mut pending = vec![(0, 0)];
+        while let Some((old, new)) = pending.pop() {
+            let node = &nodes[old];
+            if node.count > 0 {
+                continue;
+            }
+
+            let (left, right) = (node.start_index as usize, node.start_index as usize + 1);
+            let start = ordered.len();
+            ordered[new].start_index = start as u32;
+            ordered.push(nodes[left].clone());
+            ordered.push(nodes[right].clone());
+            parents.extend([new as u32; 2]);
+            pending.push((right, start + 1));
+            pending.push((left, start));
+        }
+
+        Self {
+            nodes: ordered,
+            parents,
+            skip_links: Vec::new(),
+            items,
+        }
+    }
+
+    /// Get the cost of the tree according to the Surface Area Heuristic, relative to the area of
+    /// the root. Lower costs mean less work is expected during traversal
+    pub fn sah_cost(&self) -> f32 {
+        let Some(root) = self.nodes.first() else {
+            return 0.;
+        };
 
-        Self { nodes, items }
+        let mut cost = 0.;
+        let mut stack = vec![0u32];
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index as usize];
+            if node.count == 0 {
+                cost += TRAVERSE_COST * node.volume.visible_area();
+                stack.push(node.start_index);
+                stack.push(node.start_index + 1);
+            } else {
+                cost += node.count as f32 * node.volume.visible_area();
+            }
+        }
+        cost / root.volume.visible_area()
     }
 }
 
 #[cfg(test)]
 use crate::dim2::{BvhAabb2d, Vec2};
 #[cfg(test)]
-use bevy_math::bounding::Aabb2d;
+use crate::{dim3::BvhAabb3d, random_boxes};
+#[cfg(test)]
+use bevy_math::bounding::{Aabb2d, BoundingVolume};
 
 #[test]
 fn test_bvh_new() {
@@ -187,3 +260,40 @@ fn test_bvh_new() {
     // Some of the nodes should have gotten merged
     assert!(bvh.items.len() < 5 * 2 - 1);
 }
+
+#[test]
+fn test_node_order() {
+    let boxes = random_boxes(500, 100., 2.);
+    let bvh = BvhAabb3d::new(500, boxes.into_iter().enumerate());
+
+    // Every node is reachable, and the children of a node come right after the pair holding it
+    let mut reached = 1;
+    let mut stack = vec![0];
+    while let Some(index) = stack.pop() {
+        let node = &bvh.nodes[index];
+        if node.count > 0 {
+            continue;
+        }
+        let start = node.start_index as usize;
+        assert_eq!(start, reached);
+        reached += 2;
+        stack.extend([start + 1, start]);
+    }
+    assert_eq!(reached, bvh.nodes.len());
+}
+
+#[test]
+fn test_owned_payloads() {
+    let items = (0..100).map(|i| {
+        let center = Vec2::new(i as f32, 0.);
+        (i.to_string(), Aabb2d::new(center, Vec2::splat(0.25)))
+    });
+    let bvh = BvhAabb2d::new(100, items);
+    let mut stack = bvh.create_stack();
+    let query = Aabb2d::new(Vec2::new(42., 0.), Vec2::splat(0.5));
+    let hits = bvh.traverse(&mut stack, query).collect::<Vec<_>>();
+    assert_eq!(hits, ["42"]);
+    assert!(bvh
+        .items()
+        .all(|item| item.t.parse::<f32>().unwrap() == item.volume.center().x));
+}
@@ -1,17 +1,17 @@
-use crate::{Bvh, BvhVolume};
+use crate::{Bvh, BvhItemVolume, BvhVolume};
 
-use std::fmt::{Debug, Formatter, Result};
+use core::fmt::{Debug, Formatter, Result};
 
-impl<Volume: BvhVolume, T: Copy + Debug> Debug for Bvh<Volume, T> {
+impl<Volume: BvhVolume, T: Debug, Item: BvhItemVolume<Volume>> Debug for Bvh<Volume, T, Item> {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
         writeln!(f)?;
         print_node(f, self, 0, 0)
     }
 }
 
-fn print_node<Volume: BvhVolume, T: Copy + Debug>(
+fn print_node<Volume: BvhVolume, T: Debug, Item: BvhItemVolume<Volume>>(
     f: &mut Formatter<'_>,
-    bvh: &Bvh<Volume, T>,
+    bvh: &Bvh<Volume, T, Item>,
     index: u32,
     level: usize,
 ) -> Result {
@@ -28,9 +28,9 @@ fn print_node<Volume: BvhVolume, T: Copy + Debug>(
     Ok(())
 }
 
-fn print_items<Volume: BvhVolume, T: Copy + Debug>(
+fn print_items<Volume: BvhVolume, T: Debug, Item: BvhItemVolume<Volume>>(
     f: &mut Formatter<'_>,
-    bvh: &Bvh<Volume, T>,
+    bvh: &Bvh<Volume, T, Item>,
     index: u32,
     count: u32,
     level: usize,
@@ -0,0 +1,161 @@
+//! A module with implementations for 1D support, for overlap queries on `[start, end]` intervals
+
+use crate::morton::ordered_bits;
+use crate::point::PointVolume;
+use crate::{Bvh, BvhVolume};
+
+use bevy_math::bounding::{BoundingVolume, IntersectsVolume};
+
+/// A BVH using [`Interval`] volumes
+pub type BvhInterval<T> = Bvh<Interval, T>;
+
+/// A closed interval `[min, max]`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Interval {
+    /// The start of the interval
+    pub min: f32,
+    /// The end of the interval
+    pub max: f32,
+}
+
+impl Interval {
+    /// Construct an interval from its start and end
+    pub fn new(min: f32, max: f32) -> Self {
+        debug_assert!(min <= max);
+        Self { min, max }
+    }
+
+    /// Get the length of the interval
+    #[inline(always)]
+    pub fn length(&self) -> f32 {
+        self.max - self.min
+    }
+}
+
+impl BoundingVolume for Interval {
+    type Translation = f32;
+    /// Intervals can't be rotated, so this is always the identity
+    type Rotation = ();
+    type HalfSize = f32;
+
+    #[inline(always)]
+    fn center(&self) -> Self::Translation {
+        (self.min + self.max) / 2.
+    }
+
+    #[inline(always)]
+    fn half_size(&self) -> Self::HalfSize {
+        (self.max - self.min) / 2.
+    }
+
+    #[inline(always)]
+    fn visible_area(&self) -> f32 {
+        self.length()
+    }
+
+    #[inline(always)]
+    fn contains(&self, other: &Self) -> bool {
+        other.min >= self.min && other.max <= self.max
+    }
+
+    #[inline(always)]
+    fn merge(&self, other: &Self) -> Self {
+        Self {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    #[inline(always)]
+    fn grow(&self, amount: impl Into<Self::HalfSize>) -> Self {
+        let amount = amount.into();
+        Self::new(self.min - amount, self.max + amount)
+    }
+
+    #[inline(always)]
+    fn shrink(&self, amount: impl Into<Self::HalfSize>) -> Self {
+        let amount = amount.into();
+        Self::new(self.min + amount, self.max - amount)
+    }
+
+    #[inline(always)]
+    fn scale_around_center(&self, scale: impl Into<Self::HalfSize>) -> Self {
+        let half_size = self.half_size() * scale.into();
+        Self::new(self.center() - half_size, self.center() + half_size)
+    }
+
+    #[inline(always)]
+    fn translate_by(&mut self, translation: impl Into<Self::Translation>) {
+        let translation = translation.into();
+        self.min += translation;
+        self.max += translation;
+    }
+
+    #[inline(always)]
+    fn rotate_by(&mut self, _: impl Into<Self::Rotation>) {}
+}
+
+impl BvhVolume for Interval {
+    const INFINITY: Self = Self {
+        min: -f32::INFINITY,
+        max: f32::INFINITY,
+    };
+
+    /// Intervals only have one axis, so the raw ordering of the center is used with all bits
+    #[inline(always)]
+    fn morton_code(&self) -> u64 {
+        ordered_bits(self.center() as f64, 64)
+    }
+}
+
+/// A range query, matching all intervals that overlap this interval
+impl IntersectsVolume<Interval> for Interval {
+    #[inline(always)]
+    fn intersects(&self, volume: &Interval) -> bool {
+        self.min <= volume.max && volume.min <= self.max
+    }
+}
+
+/// A stabbing query, matching all intervals that contain the point
+impl PointVolume for Interval {
+    #[inline(always)]
+    fn contains_point(&self, point: &f32) -> bool {
+        *point >= self.min && *point <= self.max
+    }
+}
+
+#[test]
+fn test_interval() {
+    // Reservations on a timeline
+    let reservations = [
+        Interval::new(0., 2.),
+        Interval::new(1.5, 3.),
+        Interval::new(3., 4.),
+        Interval::new(8., 9.5),
+        Interval::new(100_000., 100_001.),
+    ];
+    let bvh = BvhInterval::new(reservations.len(), reservations.into_iter().enumerate());
+    let mut stack = bvh.create_stack();
+
+    let mut stabbed = bvh
+        .containing_point(&mut stack, 3.)
+        .copied()
+        .collect::<Vec<_>>();
+    stabbed.sort();
+    assert_eq!(stabbed, [1, 2]);
+
+    let mut overlapping = bvh
+        .traverse(&mut stack, Interval::new(1., 1.8))
+        .copied()
+        .collect::<Vec<_>>();
+    overlapping.sort();
+    assert_eq!(overlapping, [0, 1]);
+
+    assert_eq!(bvh.traverse(&mut stack, Interval::new(4.5, 7.)).count(), 0);
+    assert_eq!(
+        bvh.containing_point(&mut stack, 100_000.5)
+            .copied()
+            .collect::<Vec<_>>(),
+        [4]
+    );
+}
@@ -1,7 +1,9 @@
 //! A module with implementations for 2D support
 
-use crate::morton::{morton_encode, MORTON_CENTER};
-use crate::{Bvh, BvhVolume};
+use crate::morton::{hilbert_encode, morton_cell, morton_encode_2d, MORTON_BITS_2D};
+use crate::{Bvh, BvhItemVolume, BvhVolume};
+
+pub use crate::obb::Obb2d;
 
 pub use bevy_math::{
     bounding::{Aabb2d, BoundingCircle, BoundingVolume},
@@ -14,6 +16,9 @@ pub type BvhAabb2d<T> = Bvh<Aabb2d, T>;
 /// A BVH using [`BoundingCircle`] volumes
 pub type BvhCircle<T> = Bvh<BoundingCircle, T>;
 
+/// A BVH using [`Obb2d`] volumes
+pub type BvhObb2d<T> = Bvh<Obb2d, T>;
+
 impl BvhVolume for Aabb2d {
     const INFINITY: Self = Self {
         min: Vec2::splat(-f32::INFINITY),
@@ -21,13 +26,23 @@ impl BvhVolume for Aabb2d {
     };
 
     #[inline(always)]
-    fn morton_code(&self) -> usize {
+    fn morton_code(&self) -> u64 {
+        let center = self.center();
+        morton_encode_2d(
+            morton_cell(center.x, MORTON_BITS_2D),
+            morton_cell(center.y, MORTON_BITS_2D),
+        )
+    }
+
+    #[inline(always)]
+    fn hilbert_code(&self) -> u64 {
         let center = self.center();
-        morton_encode(
-            (center.x + MORTON_CENTER) as usize,
-            (center.y + MORTON_CENTER) as usize,
-            0,
-            5,
+        hilbert_encode(
+            [
+                morton_cell(center.x, MORTON_BITS_2D),
+                morton_cell(center.y, MORTON_BITS_2D),
+            ],
+            MORTON_BITS_2D,
         )
     }
 }
@@ -41,13 +56,30 @@ impl BvhVolume for BoundingCircle {
     };
 
     #[inline(always)]
-    fn morton_code(&self) -> usize {
+    fn morton_code(&self) -> u64 {
+        let center = self.center();
+        morton_encode_2d(
+            morton_cell(center.x, MORTON_BITS_2D),
+            morton_cell(center.y, MORTON_BITS_2D),
+        )
+    }
+
+    #[inline(always)]
+    fn hilbert_code(&self) -> u64 {
         let center = self.center();
-        morton_encode(
-            (center.x + MORTON_CENTER) as usize,
-            (center.y + MORTON_CENTER) as usize,
-            0,
-            5,
+        hilbert_encode(
+            [
+                morton_cell(center.x, MORTON_BITS_2D),
+                morton_cell(center.y, MORTON_BITS_2D),
+            ],
+            MORTON_BITS_2D,
         )
     }
 }
+
+impl BvhItemVolume<Aabb2d> for BoundingCircle {
+    #[inline(always)]
+    fn node_volume(&self) -> Aabb2d {
+        self.aabb_2d()
+    }
+}
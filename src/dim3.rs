@@ -1,7 +1,9 @@
 //! A crate implementing 3D support for the BVH
 
-use crate::morton::{morton_encode, MORTON_CENTER};
-use crate::{Bvh, BvhVolume};
+use crate::morton::{hilbert_encode, morton_cell, morton_encode, MORTON_BITS_3D};
+use crate::{Bvh, BvhItemVolume, BvhVolume};
+
+pub use crate::obb::Obb3d;
 
 pub use bevy_math::{
     bounding::{Aabb3d, BoundingSphere, BoundingVolume},
@@ -14,6 +16,9 @@ pub type BvhAabb3d<T> = Bvh<Aabb3d, T>;
 /// A BVH using [`BoundingSphere`] volumes
 pub type BvhSphere<T> = Bvh<BoundingSphere, T>;
 
+/// A BVH using [`Obb3d`] volumes
+pub type BvhObb3d<T> = Bvh<Obb3d, T>;
+
 impl BvhVolume for Aabb3d {
     const INFINITY: Self = Self {
         min: Vec3A::splat(-f32::INFINITY),
@@ -21,13 +26,25 @@ impl BvhVolume for Aabb3d {
     };
 
     #[inline(always)]
-    fn morton_code(&self) -> usize {
+    fn morton_code(&self) -> u64 {
         let center = self.center();
         morton_encode(
-            (center.x + MORTON_CENTER) as usize,
-            (center.y + MORTON_CENTER) as usize,
-            (center.z + MORTON_CENTER) as usize,
-            5,
+            morton_cell(center.x, MORTON_BITS_3D),
+            morton_cell(center.y, MORTON_BITS_3D),
+            morton_cell(center.z, MORTON_BITS_3D),
+        )
+    }
+
+    #[inline(always)]
+    fn hilbert_code(&self) -> u64 {
+        let center = self.center();
+        hilbert_encode(
+            [
+                morton_cell(center.x, MORTON_BITS_3D),
+                morton_cell(center.y, MORTON_BITS_3D),
+                morton_cell(center.z, MORTON_BITS_3D),
+            ],
+            MORTON_BITS_3D,
         )
     }
 }
@@ -41,13 +58,68 @@ impl BvhVolume for BoundingSphere {
     };
 
     #[inline(always)]
-    fn morton_code(&self) -> usize {
+    fn morton_code(&self) -> u64 {
         let center = self.center();
         morton_encode(
-            (center.x + MORTON_CENTER) as usize,
-            (center.y + MORTON_CENTER) as usize,
-            (center.z + MORTON_CENTER) as usize,
-            5,
+            morton_cell(center.x, MORTON_BITS_3D),
+            morton_cell(center.y, MORTON_BITS_3D),
+            morton_cell(center.z, MORTON_BITS_3D),
         )
     }
+
+    #[inline(always)]
+    fn hilbert_code(&self) -> u64 {
+        let center = self.center();
+        hilbert_encode(
+            [
+                morton_cell(center.x, MORTON_BITS_3D),
+                morton_cell(center.y, MORTON_BITS_3D),
+                morton_cell(center.z, MORTON_BITS_3D),
+            ],
+            MORTON_BITS_3D,
+        )
+    }
+}
+
+impl BvhItemVolume<Aabb3d> for BoundingSphere {
+    #[inline(always)]
+    fn node_volume(&self) -> Aabb3d {
+        self.aabb_3d()
+    }
+}
+
+#[cfg(test)]
+use bevy_math::{bounding::RayCast3d, Dir3A};
+
+#[test]
+fn test_item_volumes() {
+    // Spheres on the items, with boxes for the nodes
+    let spheres = [
+        BoundingSphere::new(Vec3A::ZERO, 1.),
+        BoundingSphere::new(Vec3A::X * 3., 1.),
+        BoundingSphere::new(Vec3A::Y * 10., 2.),
+    ];
+    let bvh = Bvh::<Aabb3d, usize, BoundingSphere>::new(
+        spheres.len(),
+        spheres.iter().copied().enumerate(),
+    );
+    let mut stack = bvh.create_stack();
+
+    // The corner of the box around the first sphere is outside the sphere itself
+    let corner = Aabb3d::new(Vec3A::splat(0.9), Vec3A::splat(0.05));
+    assert_eq!(bvh.traverse(&mut stack, corner).count(), 0);
+    assert_eq!(
+        bvh.containing_point(&mut stack, Vec3A::splat(0.9)).count(),
+        0
+    );
+
+    let ray = RayCast3d::new(Vec3A::new(3., -5., 0.), Dir3A::Y, 20.);
+    let mut hits = bvh.traverse(&mut stack, ray).copied().collect::<Vec<_>>();
+    hits.sort();
+    assert_eq!(hits, [1]);
+    let ray = RayCast3d::new(Vec3A::new(-5., 10., 0.), Dir3A::X, 20.);
+    assert_eq!(
+        bvh.traverse(&mut stack, ray).copied().collect::<Vec<_>>(),
+        [2]
+    );
 }
@@ -0,0 +1,232 @@
+//! A module with implementations for axis-aligned boxes with any number of dimensions, for example
+//! for spatio-temporal (x, y, z, t) indexing or searches in a feature space
+
+use crate::morton::{bounded_cell, morton_cell, morton_encode_n, MORTON_BITS_3D};
+use crate::point::PointVolume;
+use crate::{Bvh, BvhVolume, SpaceFillingCurve};
+
+use bevy_math::bounding::{BoundingVolume, IntersectsVolume};
+
+/// A BVH using [`AabbN`] volumes
+pub type BvhAabbN<const D: usize, T> = Bvh<AabbN<D>, T>;
+
+/// An axis-aligned bounding box with `D` dimensions. Morton codes are supported for 1 to 6
+/// dimensions.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AabbN<const D: usize> {
+    /// The minimum point of the box
+    pub min: [f32; D],
+    /// The maximum point of the box
+    pub max: [f32; D],
+}
+
+impl<const D: usize> AabbN<D> {
+    /// Construct an AABB from its center and half size
+    pub fn new(center: [f32; D], half_size: [f32; D]) -> Self {
+        debug_assert!(half_size.iter().all(|&h| h >= 0.));
+        Self {
+            min: core::array::from_fn(|i| center[i] - half_size[i]),
+            max: core::array::from_fn(|i| center[i] + half_size[i]),
+        }
+    }
+
+    /// Get the closest point in the box to the point
+    #[inline(always)]
+    pub fn closest_point(&self, point: [f32; D]) -> [f32; D] {
+        core::array::from_fn(|i| point[i].clamp(self.min[i], self.max[i]))
+    }
+}
+
+impl<const D: usize> BoundingVolume for AabbN<D> {
+    type Translation = [f32; D];
+    /// A rotation matrix, where `rotation[i][j]` is the element in row `i` and column `j`
+    type Rotation = [[f32; D]; D];
+    type HalfSize = [f32; D];
+
+    #[inline(always)]
+    fn center(&self) -> Self::Translation {
+        core::array::from_fn(|i| (self.min[i] + self.max[i]) / 2.)
+    }
+
+    #[inline(always)]
+    fn half_size(&self) -> Self::HalfSize {
+        core::array::from_fn(|i| (self.max[i] - self.min[i]) / 2.)
+    }
+
+    /// Get the length for a single dimension, or the sum of the faces' areas on one side of the
+    /// box for more dimensions
+    #[inline(always)]
+    fn visible_area(&self) -> f32 {
+        let size: [f32; D] = core::array::from_fn(|i| self.max[i] - self.min[i]);
+        if D == 1 {
+            return size[0];
+        }
+        (0..D)
+            .map(|face| {
+                (0..D)
+                    .filter(|&axis| axis != face)
+                    .map(|axis| size[axis])
+                    .product::<f32>()
+            })
+            .sum()
+    }
+
+    #[inline(always)]
+    fn contains(&self, other: &Self) -> bool {
+        (0..D).all(|i| other.min[i] >= self.min[i] && other.max[i] <= self.max[i])
+    }
+
+    #[inline(always)]
+    fn merge(&self, other: &Self) -> Self {
+        Self {
+            min: core::array::from_fn(|i| self.min[i].min(other.min[i])),
+            max: core::array::from_fn(|i| self.max[i].max(other.max[i])),
+        }
+    }
+
+    #[inline(always)]
+    fn grow(&self, amount: impl Into<Self::HalfSize>) -> Self {
+        let amount = amount.into();
+        Self {
+            min: core::array::from_fn(|i| self.min[i] - amount[i]),
+            max: core::array::from_fn(|i| self.max[i] + amount[i]),
+        }
+    }
+
+    #[inline(always)]
+    fn shrink(&self, amount: impl Into<Self::HalfSize>) -> Self {
+        let amount = amount.into();
+        Self {
+            min: core::array::from_fn(|i| self.min[i] + amount[i]),
+            max: core::array::from_fn(|i| self.max[i] - amount[i]),
+        }
+    }
+
+    #[inline(always)]
+    fn scale_around_center(&self, scale: impl Into<Self::HalfSize>) -> Self {
+        let scale = scale.into();
+        let half_size = self.half_size();
+        Self::new(
+            self.center(),
+            core::array::from_fn(|i| half_size[i] * scale[i]),
+        )
+    }
+
+    #[inline(always)]
+    fn translate_by(&mut self, translation: impl Into<Self::Translation>) {
+        let translation = translation.into();
+        for (i, offset) in translation.into_iter().enumerate() {
+            self.min[i] += offset;
+            self.max[i] += offset;
+        }
+    }
+
+    /// Rotates the bounding volume around the origin by the given rotation.
+    ///
+    /// The result is an AABB that encompasses the rotated shape, so it may be looser than the
+    /// original.
+    fn rotate_by(&mut self, rotation: impl Into<Self::Rotation>) {
+        let rotation = rotation.into();
+        let (center, half_size) = (self.center(), self.half_size());
+        *self = Self::new(
+            core::array::from_fn(|i| (0..D).map(|j| rotation[i][j] * center[j]).sum()),
+            core::array::from_fn(|i| (0..D).map(|j| rotation[i][j].abs() * half_size[j]).sum()),
+        );
+    }
+}
+
+impl<const D: usize> BvhVolume for AabbN<D> {
+    const INFINITY: Self = Self {
+        min: [-f32::INFINITY; D],
+        max: [f32::INFINITY; D],
+    };
+
+    #[inline(always)]
+    fn morton_code(&self) -> u64 {
+        // Keep the most significant bits of each coordinate when there are too many dimensions
+        // to fit the range of a 3D code
+        let bits = 64 / D as u32;
+        let range = bits.max(MORTON_BITS_3D);
+        let center = self.center();
+        morton_encode_n::<D>(core::array::from_fn(|i| {
+            morton_cell(center[i], range) >> (range - bits)
+        }))
+    }
+
+    /// Spread the cells over the bounds, so small or normalized coordinates don't all end up in
+    /// the same unit-sized cell. There is no hilbert curve, both curves use the morton code
+    #[inline(always)]
+    fn curve_code(&self, _curve: SpaceFillingCurve, bounds: &Self) -> u64 {
+        if (0..D).any(|i| !(bounds.max[i] - bounds.min[i]).is_finite()) {
+            return self.morton_code();
+        }
+        let bits = 64 / D as u32;
+        let center = self.center();
+        morton_encode_n::<D>(core::array::from_fn(|i| {
+            bounded_cell(
+                center[i] as f64,
+                bounds.min[i] as f64,
+                bounds.max[i] as f64,
+                bits,
+            )
+        }))
+    }
+}
+
+impl<const D: usize> IntersectsVolume<AabbN<D>> for AabbN<D> {
+    #[inline(always)]
+    fn intersects(&self, volume: &AabbN<D>) -> bool {
+        (0..D).all(|i| self.min[i] <= volume.max[i] && volume.min[i] <= self.max[i])
+    }
+}
+
+impl<const D: usize> PointVolume for AabbN<D> {
+    #[inline(always)]
+    fn contains_point(&self, point: &[f32; D]) -> bool {
+        (0..D).all(|i| point[i] >= self.min[i] && point[i] <= self.max[i])
+    }
+}
+
+#[cfg(test)]
+use crate::random_boxes;
+
+#[test]
+fn test_aabb_n() {
+    // Objects moving through space over time, as (x, y, z, t) boxes
+    let boxes = (0..50).map(|i| {
+        let i = i as f32;
+        AabbN::new([i, 0., -i, i * 0.5], [0.5, 0.5, 0.5, 0.25])
+    });
+    let bvh = BvhAabbN::new(50, boxes.enumerate());
+    let mut stack = bvh.create_stack();
+
+    let query = AabbN::new([10., 0., -10., 5.], [1.6, 1., 1., 1.]);
+    let mut hits = bvh.traverse(&mut stack, query).copied().collect::<Vec<_>>();
+    hits.sort();
+    assert_eq!(hits, [9, 10, 11]);
+
+    // Outside the time range of the query
+    let query = AabbN::new([10., 0., -10., 1.], [1.6, 1., 1., 1.]);
+    assert_eq!(bvh.traverse(&mut stack, query).count(), 0);
+
+    let hits = bvh
+        .containing_point(&mut stack, [20.5, 0.5, -20., 10.2])
+        .copied()
+        .collect::<Vec<_>>();
+    assert_eq!(hits, [20]);
+
+    // Coordinates normalized to [0, 1] get trees as good as the same data scaled up
+    let boxes = random_boxes(40_000, 1., 0.005);
+    let sah_cost = |scale: f32| {
+        let boxes = boxes.chunks(2).map(|pair| {
+            let (a, b) = (pair[0], pair[1]);
+            AabbN {
+                min: [a.min.x, a.min.y, a.min.z, b.min.x].map(|x| x * scale),
+                max: [a.max.x, a.max.y, a.max.z, b.max.x].map(|x| x * scale),
+            }
+        });
+        BvhAabbN::new(20_000, boxes.enumerate()).sah_cost()
+    };
+    let (normalized, scaled) = (sah_cost(1.), sah_cost(1000.));
+    assert!(normalized < scaled * 1.05, "{normalized} vs {scaled}");
+}
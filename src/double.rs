@@ -0,0 +1,468 @@
+//! A module with double-precision 3D volumes, for worlds too large for `f32` positions.
+//!
+//! Trees of these volumes are built from morton codes with the cells spread over the bounds of the
+//! items, so items clustered far from the origin still get distinct cells. On their own, the codes
+//! of [`BvhVolume::morton_code`] are based on the ordering of the raw float bits, with cells that
+//! grow with the distance to the origin.
+
+use crate::morton::{bounded_cell, morton_encode, ordered_bits};
+use crate::{Bvh, BvhVolume, SpaceFillingCurve};
+
+use bevy_math::{
+    bounding::{BoundingVolume, IntersectsVolume},
+    DMat3, DQuat, DVec3,
+};
+
+/// The number of bits per axis used for the morton code
+const MORTON_BITS: u32 = 21;
+
+#[inline(always)]
+fn sqrt(x: f64) -> f64 {
+    #[cfg(feature = "std")]
+    return x.sqrt();
+    #[cfg(not(feature = "std"))]
+    return libm::sqrt(x);
+}
+
+/// A BVH using [`DAabb3d`] volumes
+pub type BvhDAabb3d<T> = Bvh<DAabb3d, T>;
+
+/// A BVH using [`DBoundingSphere`] volumes
+pub type BvhDSphere<T> = Bvh<DBoundingSphere, T>;
+
+/// A double-precision 3D axis-aligned bounding box
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DAabb3d {
+    /// The minimum point of the box
+    pub min: DVec3,
+    /// The maximum point of the box
+    pub max: DVec3,
+}
+
+impl DAabb3d {
+    /// Construct an AABB from its center and half size
+    pub fn new(center: DVec3, half_size: DVec3) -> Self {
+        debug_assert!(half_size.cmpge(DVec3::ZERO).all());
+        Self {
+            min: center - half_size,
+            max: center + half_size,
+        }
+    }
+
+    /// Get the closest point in the box to the point
+    #[inline(always)]
+    pub fn closest_point(&self, point: DVec3) -> DVec3 {
+        point.clamp(self.min, self.max)
+    }
+}
+
+impl BoundingVolume for DAabb3d {
+    type Translation = DVec3;
+    type Rotation = DQuat;
+    type HalfSize = DVec3;
+
+    #[inline(always)]
+    fn center(&self) -> Self::Translation {
+        (self.min + self.max) / 2.
+    }
+
+    #[inline(always)]
+    fn half_size(&self) -> Self::HalfSize {
+        (self.max - self.min) / 2.
+    }
+
+    #[inline(always)]
+    fn visible_area(&self) -> f32 {
+        let b = self.max - self.min;
+        (b.x * (b.y + b.z) + b.y * b.z) as f32
+    }
+
+    #[inline(always)]
+    fn contains(&self, other: &Self) -> bool {
+        other.min.cmpge(self.min).all() && other.max.cmple(self.max).all()
+    }
+
+    #[inline(always)]
+    fn merge(&self, other: &Self) -> Self {
+        Self {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    #[inline(always)]
+    fn grow(&self, amount: impl Into<Self::HalfSize>) -> Self {
+        let amount = amount.into();
+        Self {
+            min: self.min - amount,
+            max: self.max + amount,
+        }
+    }
+
+    #[inline(always)]
+    fn shrink(&self, amount: impl Into<Self::HalfSize>) -> Self {
+        let amount = amount.into();
+        Self {
+            min: self.min + amount,
+            max: self.max - amount,
+        }
+    }
+
+    #[inline(always)]
+    fn scale_around_center(&self, scale: impl Into<Self::HalfSize>) -> Self {
+        Self::new(self.center(), self.half_size() * scale.into())
+    }
+
+    #[inline(always)]
+    fn translate_by(&mut self, translation: impl Into<Self::Translation>) {
+        let translation = translation.into();
+        self.min += translation;
+        self.max += translation;
+    }
+
+    /// Rotates the bounding volume around the origin by the given rotation.
+    ///
+    /// The result is an AABB that encompasses the rotated shape, so it may be looser than the
+    /// original.
+    #[inline(always)]
+    fn rotate_by(&mut self, rotation: impl Into<Self::Rotation>) {
+        let rotation = DMat3::from_quat(rotation.into());
+        let half_size = rotation.abs() * self.half_size();
+        *self = Self::new(rotation * self.center(), half_size);
+    }
+}
+
+impl BvhVolume for DAabb3d {
+    const INFINITY: Self = Self {
+        min: DVec3::NEG_INFINITY,
+        max: DVec3::INFINITY,
+    };
+
+    #[inline(always)]
+    fn morton_code(&self) -> u64 {
+        morton_code(self.center())
+    }
+
+    /// Double precision volumes have no hilbert curve, both curves use the morton code
+    #[inline(always)]
+    fn curve_code(&self, _curve: SpaceFillingCurve, bounds: &Self) -> u64 {
+        bounded_morton_code(self.center(), bounds.min, bounds.max)
+    }
+}
+
+/// A double-precision bounding sphere
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DBoundingSphere {
+    /// The center of the sphere
+    pub center: DVec3,
+    /// The radius of the sphere
+    pub radius: f64,
+}
+
+impl DBoundingSphere {
+    /// Construct a bounding sphere from its center and radius
+    pub fn new(center: DVec3, radius: f64) -> Self {
+        debug_assert!(radius >= 0.);
+        Self { center, radius }
+    }
+
+    /// Get the [`DAabb3d`] containing the sphere
+    #[inline(always)]
+    pub fn aabb_3d(&self) -> DAabb3d {
+        DAabb3d::new(self.center, DVec3::splat(self.radius))
+    }
+}
+
+impl BoundingVolume for DBoundingSphere {
+    type Translation = DVec3;
+    type Rotation = DQuat;
+    type HalfSize = f64;
+
+    #[inline(always)]
+    fn center(&self) -> Self::Translation {
+        self.center
+    }
+
+    #[inline(always)]
+    fn half_size(&self) -> Self::HalfSize {
+        self.radius
+    }
+
+    #[inline(always)]
+    fn visible_area(&self) -> f32 {
+        (2. * core::f64::consts::PI * self.radius * self.radius) as f32
+    }
+
+    #[inline(always)]
+    fn contains(&self, other: &Self) -> bool {
+        let diff = self.radius - other.radius;
+        diff >= 0. && self.center.distance_squared(other.center) <= diff * diff
+    }
+
+    #[inline(always)]
+    fn merge(&self, other: &Self) -> Self {
+        let diff = other.center - self.center;
+        let length = diff.length();
+        if self.radius >= length + other.radius {
+            return *self;
+        }
+        if other.radius >= length + self.radius {
+            return *other;
+        }
+        let dir = diff / length;
+        Self::new(
+            (self.center + other.center) / 2. + dir * ((other.radius - self.radius) / 2.),
+            (length + self.radius + other.radius) / 2.,
+        )
+    }
+
+    #[inline(always)]
+    fn grow(&self, amount: impl Into<Self::HalfSize>) -> Self {
+        Self::new(self.center, self.radius + amount.into())
+    }
+
+    #[inline(always)]
+    fn shrink(&self, amount: impl Into<Self::HalfSize>) -> Self {
+        Self::new(self.center, self.radius - amount.into())
+    }
+
+    #[inline(always)]
+    fn scale_around_center(&self, scale: impl Into<Self::HalfSize>) -> Self {
+        Self::new(self.center, self.radius * scale.into())
+    }
+
+    #[inline(always)]
+    fn translate_by(&mut self, translation: impl Into<Self::Translation>) {
+        self.center += translation.into();
+    }
+
+    #[inline(always)]
+    fn rotate_by(&mut self, rotation: impl Into<Self::Rotation>) {
+        self.center = rotation.into() * self.center;
+    }
+}
+
+impl BvhVolume for DBoundingSphere {
+    const INFINITY: Self = Self {
+        center: DVec3::ZERO,
+        radius: f64::INFINITY,
+    };
+
+    #[inline(always)]
+    fn morton_code(&self) -> u64 {
+        morton_code(self.center)
+    }
+
+    /// Double precision volumes have no hilbert curve, both curves use the morton code
+    #[inline(always)]
+    fn curve_code(&self, _curve: SpaceFillingCurve, bounds: &Self) -> u64 {
+        let radius = DVec3::splat(bounds.radius);
+        bounded_morton_code(self.center, bounds.center - radius, bounds.center + radius)
+    }
+}
+
+#[inline(always)]
+fn morton_code(center: DVec3) -> u64 {
+    morton_encode(
+        ordered_bits(center.x, MORTON_BITS),
+        ordered_bits(center.y, MORTON_BITS),
+        ordered_bits(center.z, MORTON_BITS),
+    )
+}
+
+/// Get the morton code with the cells spread over the bounds, falling back to the ordered bits of
+/// [`morton_code`] for unbounded volumes
+#[inline(always)]
+fn bounded_morton_code(center: DVec3, min: DVec3, max: DVec3) -> u64 {
+    if !(max - min).is_finite() {
+        return morton_code(center);
+    }
+    morton_encode(
+        bounded_cell(center.x, min.x, max.x, MORTON_BITS),
+        bounded_cell(center.y, min.y, max.y, MORTON_BITS),
+        bounded_cell(center.z, min.z, max.z, MORTON_BITS),
+    )
+}
+
+impl IntersectsVolume<DAabb3d> for DAabb3d {
+    #[inline(always)]
+    fn intersects(&self, volume: &DAabb3d) -> bool {
+        self.min.cmple(volume.max).all() && self.max.cmpge(volume.min).all()
+    }
+}
+
+impl IntersectsVolume<DBoundingSphere> for DAabb3d {
+    #[inline(always)]
+    fn intersects(&self, volume: &DBoundingSphere) -> bool {
+        let closest_point = self.closest_point(volume.center);
+        closest_point.distance_squared(volume.center) <= volume.radius * volume.radius
+    }
+}
+
+impl IntersectsVolume<DBoundingSphere> for DBoundingSphere {
+    #[inline(always)]
+    fn intersects(&self, volume: &DBoundingSphere) -> bool {
+        let radius = self.radius + volume.radius;
+        self.center.distance_squared(volume.center) <= radius * radius
+    }
+}
+
+impl IntersectsVolume<DAabb3d> for DBoundingSphere {
+    #[inline(always)]
+    fn intersects(&self, volume: &DAabb3d) -> bool {
+        volume.intersects(self)
+    }
+}
+
+/// A double-precision ray cast intersection test
+#[derive(Clone, Copy, Debug)]
+pub struct DRayCast3d {
+    /// The origin of the ray
+    pub origin: DVec3,
+    /// The normalized direction of the ray
+    pub direction: DVec3,
+    /// The maximum distance for the ray
+    pub max: f64,
+    direction_recip: DVec3,
+}
+
+impl DRayCast3d {
+    /// Construct a ray cast from its origin, direction and maximum distance. The direction gets
+    /// normalized.
+    pub fn new(origin: DVec3, direction: DVec3, max: f64) -> Self {
+        let direction = direction.normalize();
+        Self {
+            origin,
+            direction,
+            max,
+            direction_recip: direction.recip(),
+        }
+    }
+
+    /// Get the distance of an intersection with a [`DAabb3d`], if any
+    pub fn aabb_intersection_at(&self, aabb: &DAabb3d) -> Option<f64> {
+        // Axes the ray doesn't move along don't limit the distance if the origin is inside the
+        // slab, and miss otherwise. Dividing by zero would produce NaN on the boundary
+        let moving = self.direction.cmpne(DVec3::ZERO);
+        let inside = self.origin.cmpge(aabb.min) & self.origin.cmple(aabb.max);
+        if !(moving | inside).all() {
+            return None;
+        }
+
+        let t1 = (aabb.min - self.origin) * self.direction_recip;
+        let t2 = (aabb.max - self.origin) * self.direction_recip;
+        let near = DVec3::select(moving, t1.min(t2), DVec3::NEG_INFINITY);
+        let far = DVec3::select(moving, t1.max(t2), DVec3::INFINITY);
+
+        let entry = near.max_element().max(0.);
+        let exit = far.min_element().min(self.max);
+
+        (entry <= exit).then_some(entry)
+    }
+
+    /// Get the distance of an intersection with a [`DBoundingSphere`], if any
+    pub fn sphere_intersection_at(&self, sphere: &DBoundingSphere) -> Option<f64> {
+        let offset = self.origin - sphere.center;
+        let projected = offset.dot(self.direction);
+        let closest_point = offset - projected * self.direction;
+        let distance_squared = sphere.radius * sphere.radius - closest_point.length_squared();
+        if distance_squared < 0.
+            || (projected > 0. && offset.length_squared() > sphere.radius * sphere.radius)
+        {
+            return None;
+        }
+
+        let distance = (-projected - sqrt(distance_squared)).max(0.);
+        (distance <= self.max).then_some(distance)
+    }
+}
+
+impl IntersectsVolume<DAabb3d> for DRayCast3d {
+    #[inline(always)]
+    fn intersects(&self, volume: &DAabb3d) -> bool {
+        self.aabb_intersection_at(volume).is_some()
+    }
+}
+
+impl IntersectsVolume<DBoundingSphere> for DRayCast3d {
+    #[inline(always)]
+    fn intersects(&self, volume: &DBoundingSphere) -> bool {
+        self.sphere_intersection_at(volume).is_some()
+    }
+}
+
+#[cfg(test)]
+use {
+    crate::random_boxes,
+    bevy_math::{
+        bounding::{Aabb3d, RayCast3d},
+        Dir3A, Vec3A,
+    },
+};
+
+#[test]
+fn test_double() {
+    // Boxes 1cm apart, 120km away from the origin
+    let far = DVec3::new(120_000., 3_000., -80_000.);
+    let boxes = (0..100)
+        .map(|i| DAabb3d::new(far + DVec3::X * (i as f64 * 0.02), DVec3::splat(0.005)))
+        .collect::<Vec<_>>();
+    let bvh = BvhDAabb3d::new(boxes.len(), boxes.iter().copied().enumerate());
+    let mut stack = bvh.create_stack();
+
+    let query = DAabb3d::new(far + DVec3::X * 0.5, DVec3::splat(0.001));
+    assert_eq!(
+        bvh.traverse(&mut stack, query).copied().collect::<Vec<_>>(),
+        [25]
+    );
+
+    let ray = DRayCast3d::new(far + DVec3::new(0.4, 10., 0.), DVec3::NEG_Y, 20.);
+    assert_eq!(
+        bvh.traverse(&mut stack, ray).copied().collect::<Vec<_>>(),
+        [20]
+    );
+    let distance = ray.aabb_intersection_at(&boxes[20]).unwrap();
+    assert!((distance - 9.995).abs() < 1e-9);
+
+    // A ray starting exactly on a face, without moving along that axis, touches the box like
+    // bevy's ray cast does
+    let origin = DVec3::new(boxes[20].min.x, far.y + 10., far.z);
+    let on_face = DRayCast3d::new(origin, DVec3::NEG_Y, 20.);
+    assert_eq!(
+        bvh.traverse(&mut stack, on_face)
+            .copied()
+            .collect::<Vec<_>>(),
+        [20]
+    );
+    let unit = Aabb3d::new(Vec3A::ZERO, Vec3A::splat(0.5));
+    let expected = RayCast3d::new(Vec3A::new(-0.5, 2., 0.), Dir3A::NEG_Y, 5.)
+        .aabb_intersection_at(&unit)
+        .map(f64::from);
+    let on_face = DRayCast3d::new(DVec3::new(-0.5, 2., 0.), DVec3::NEG_Y, 5.);
+    let unit = DAabb3d::new(DVec3::ZERO, DVec3::splat(0.5));
+    assert_eq!(on_face.aabb_intersection_at(&unit), expected);
+    assert_eq!(expected, Some(1.5));
+
+    let sphere = DBoundingSphere::new(far + DVec3::X * 0.3, 0.016);
+    let mut hits = bvh
+        .traverse(&mut stack, sphere)
+        .copied()
+        .collect::<Vec<_>>();
+    hits.sort();
+    assert_eq!(hits, [14, 15, 16]);
+    assert!(ray.intersects(&DBoundingSphere::new(far + DVec3::X * 0.4, 0.01)));
+
+    // Many objects clustered in a 200m cube far from the origin get trees as good as the same
+    // objects at the origin
+    let cluster = |offset: DVec3| {
+        let boxes = random_boxes(20_000, 200., 1.)
+            .into_iter()
+            .map(|aabb| DAabb3d {
+                min: aabb.min.as_dvec3() + offset,
+                max: aabb.max.as_dvec3() + offset,
+            });
+        BvhDAabb3d::new(20_000, boxes.enumerate()).sah_cost()
+    };
+    let (near, far) = (cluster(DVec3::ZERO), cluster(DVec3::splat(120_000.)));
+    assert!(far < near * 1.05, "{far} vs {near}");
+}
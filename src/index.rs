@@ -0,0 +1,217 @@
+//! A module with a BVH that only stores the indices of its items. The volumes stay in a slice owned
+//! by the caller, which is passed along with each query.
+
+use crate::traverse::Stack;
+use crate::{ceil_log2, Bvh, BvhItemVolume, BvhNode, BvhVolume, SpaceFillingCurve};
+
+use alloc::{vec, vec::Vec};
+
+use bevy_math::bounding::IntersectsVolume;
+
+/// A BVH over a caller-owned slice of volumes, see [`IndexBvh::new`]
+pub struct IndexBvh<Volume: BvhVolume> {
+    nodes: Vec<BvhNode<Volume>>,
+    leaf_to_input: Vec<u32>,
+    input_to_leaf: Vec<u32>,
+}
+
+impl<Volume: BvhVolume> Default for IndexBvh<Volume> {
+    fn default() -> Self {
+        Self {
+            nodes: Vec::new(),
+            leaf_to_input: Vec::new(),
+            input_to_leaf: Vec::new(),
+        }
+    }
+}
+
+impl<Volume: BvhVolume> IndexBvh<Volume> {
+    /// Construct a BVH over the volumes. The same slice, or one with the same volumes in the same
+    /// order, has to be passed to the queries
+    pub fn new<Item: BvhItemVolume<Volume>>(volumes: &[Item]) -> Self {
+        Self::new_with_curve(volumes, SpaceFillingCurve::Morton)
+    }
+
+    /// Construct a BVH over the volumes, ordering them along the provided curve
+    pub fn new_with_curve<Item: BvhItemVolume<Volume>>(
+        volumes: &[Item],
+        curve: SpaceFillingCurve,
+    ) -> Self {
+        let items = volumes
+            .iter()
+            .enumerate()
+            .map(|(index, volume)| (index as u32, volume.node_volume()));
+        let bvh = Bvh::<Volume, u32>::new_with_curve(volumes.len(), items, curve);
+
+        let leaf_to_input = bvh.items.iter().map(|item| item.t).collect::<Vec<_>>();
+        let mut input_to_leaf = vec![0; leaf_to_input.len()];
+        for (leaf, input) in leaf_to_input.iter().enumerate() {
+            input_to_leaf[*input as usize] = leaf as u32;
+        }
+
+        Self {
+            nodes: bvh.nodes,
+            leaf_to_input,
+            input_to_leaf,
+        }
+    }
+
+    /// Get the number of nodes in the BVH
+    pub fn n_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Get the number of items in the BVH
+    pub fn n_items(&self) -> usize {
+        self.leaf_to_input.len()
+    }
+
+    /// Get an iterator over the BVH's nodes. The leaves point into [`IndexBvh::leaf_to_input`]
+    pub fn nodes(&self) -> impl Iterator<Item = &BvhNode<Volume>> {
+        self.nodes.iter()
+    }
+
+    /// Get the input index stored at each leaf position
+    pub fn leaf_to_input(&self) -> &[u32] {
+        &self.leaf_to_input
+    }
+
+    /// Get the leaf position of each input index, the inverse of [`IndexBvh::leaf_to_input`]
+    pub fn input_to_leaf(&self) -> &[u32] {
+        &self.input_to_leaf
+    }
+
+    /// Create a stack with the right size for the BVH
+    pub fn create_stack(&self) -> Stack {
+        let mut stack = Stack::default();
+        stack.reserve_exact(ceil_log2(self.n_items()) + 10);
+        stack
+    }
+
+    /// Traverse the BVH with the provided [`IntersectsVolume`] test, yielding the input indices of
+    /// the items that pass. Item volumes are read from `volumes`, in input order
+    pub fn traverse<'a, Item, Test>(
+        &'a self,
+        stack: &'a mut Stack,
+        volumes: &'a [Item],
+        tester: Test,
+    ) -> IndexTraverser<'a, Volume, Item, Test>
+    where
+        Item: BvhItemVolume<Volume>,
+        Test: IntersectsVolume<Volume> + IntersectsVolume<Item>,
+    {
+        debug_assert_eq!(volumes.len(), self.n_items());
+        stack.clear();
+        if !self.nodes.is_empty() {
+            stack.push_back(0);
+        }
+
+        IndexTraverser {
+            bvh: self,
+            volumes,
+            tester,
+            stack,
+            leaves: 0..0,
+        }
+    }
+}
+
+/// An iterator that traverses the [`IndexBvh`] using the provided [`IntersectsVolume`] test
+pub struct IndexTraverser<'a, Volume: BvhVolume, Item, Test> {
+    bvh: &'a IndexBvh<Volume>,
+    volumes: &'a [Item],
+    /// The test used in the traverser
+    pub tester: Test,
+    stack: &'a mut Stack,
+    leaves: core::ops::Range<usize>,
+}
+
+impl<'a, Volume, Item, Test> Iterator for IndexTraverser<'a, Volume, Item, Test>
+where
+    Volume: BvhVolume,
+    Item: BvhItemVolume<Volume>,
+    Test: IntersectsVolume<Volume> + IntersectsVolume<Item>,
+{
+    type Item = usize;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            for leaf in self.leaves.by_ref() {
+                let input = self.bvh.leaf_to_input[leaf] as usize;
+                if IntersectsVolume::<Item>::intersects(&self.tester, &self.volumes[input]) {
+                    return Some(input);
+                }
+            }
+
+            let node = &self.bvh.nodes[self.stack.pop_front()? as usize];
+            if !IntersectsVolume::<Volume>::intersects(&self.tester, &node.volume) {
+                continue;
+            }
+
+            let start = node.start_index as usize;
+            match node.count {
+                0 => self.stack.extend([node.start_index, node.start_index + 1]),
+                count => self.leaves = start..start + count as usize,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+use crate::{
+    dim3::{Aabb3d, BoundingSphere, BoundingVolume, Vec3A},
+    random_boxes, random_point,
+};
+
+#[test]
+fn test_index_bvh() {
+    let spheres = random_boxes(1000, 100., 1.)
+        .iter()
+        .map(Aabb3d::bounding_sphere)
+        .collect::<Vec<_>>();
+    let bvh = IndexBvh::<Aabb3d>::new(&spheres);
+    let expected = Bvh::<Aabb3d, usize, BoundingSphere>::new(
+        spheres.len(),
+        spheres.iter().copied().enumerate(),
+    );
+    let mut stack = bvh.create_stack();
+
+    for (leaf, input) in bvh.leaf_to_input().iter().enumerate() {
+        assert_eq!(bvh.input_to_leaf()[*input as usize] as usize, leaf);
+    }
+
+    // Data sorted to leaf order lines up with the leaves
+    let sorted = bvh
+        .leaf_to_input()
+        .iter()
+        .map(|input| spheres[*input as usize])
+        .collect::<Vec<_>>();
+    let leaves = bvh.nodes().filter(|node| node.count > 0);
+    for node in leaves {
+        let start = node.start_index as usize;
+        for sphere in &sorted[start..start + node.count as usize] {
+            assert!(node.volume.contains(&sphere.aabb_3d()));
+        }
+    }
+
+    for _ in 0..50 {
+        let query = Aabb3d::new(random_point(100.), Vec3A::splat(5.));
+        let mut hits = bvh
+            .traverse(&mut stack, &spheres, query)
+            .collect::<Vec<_>>();
+        let mut expected = expected
+            .traverse(&mut stack, query)
+            .copied()
+            .collect::<Vec<_>>();
+        hits.sort();
+        expected.sort();
+        assert_eq!(hits, expected);
+    }
+
+    let empty = IndexBvh::<Aabb3d>::new::<Aabb3d>(&[]);
+    let query = Aabb3d::new(Vec3A::ZERO, Vec3A::ONE);
+    assert_eq!(
+        empty.traverse::<Aabb3d, _>(&mut stack, &[], query).count(),
+        0
+    );
+}
@@ -0,0 +1,595 @@
+//! A module with k-DOP (discrete oriented polytope) volumes. A k-DOP bounds a shape with slabs
+//! along a fixed set of axes, giving much tighter bounds than an AABB for most shapes while
+//! keeping merges and overlap tests cheap.
+
+use crate::morton::{
+    morton_cell, morton_encode, morton_encode_2d, MORTON_BITS_2D, MORTON_BITS_3D,
+};
+use crate::{Bvh, BvhVolume};
+
+use core::f32::consts::{FRAC_1_SQRT_2, PI};
+use core::fmt::Debug;
+
+use bevy_math::{
+    bounding::{
+        Aabb2d, Aabb3d, BoundingCircle, BoundingSphere, BoundingVolume, IntersectsVolume,
+        RayCast2d, RayCast3d,
+    },
+    Quat, Rot2, Vec2, Vec3A,
+};
+
+/// A k-DOP with `N` slabs, bounding a shape along `N` fixed axes.
+///
+/// The axes are determined by `N`, see [`Dop8`], [`Dop14`], [`Dop18`] and [`Dop26`].
+/// The first axes are always the coordinate axes, so the k-DOP always contains its AABB.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KDop<const N: usize> {
+    /// The minimum projection on each of the axes
+    pub min: [f32; N],
+    /// The maximum projection on each of the axes
+    pub max: [f32; N],
+}
+
+/// A 2D 8-DOP, using the coordinate axes and the diagonals
+pub type Dop8 = KDop<4>;
+
+/// A 3D 14-DOP, using the coordinate axes and the corners of a cube
+pub type Dop14 = KDop<7>;
+
+/// A 3D 18-DOP, using the coordinate axes and the edges of a cube
+pub type Dop18 = KDop<9>;
+
+/// A 3D 26-DOP, using the coordinate axes, and the corners and edges of a cube
+pub type Dop26 = KDop<13>;
+
+/// A BVH using [`KDop`] volumes
+pub type BvhKDop<const N: usize, T> = Bvh<KDop<N>, T>;
+
+/// The axes of a [`KDop`] with `N` slabs, and the space they live in
+pub trait KDopAxes<const N: usize> {
+    /// The type of points in the space of the k-DOP
+    type Point: Clone + Copy + PartialEq + Debug;
+    /// The type of rotations in the space of the k-DOP
+    type Rotation: Clone + Copy + PartialEq;
+
+    /// The number of dimensions, the first `DIMENSIONS` axes are the coordinate axes
+    const DIMENSIONS: usize;
+    /// The normalized axes of the slabs
+    const AXES: [Self::Point; N];
+
+    /// Get the dot product of two points
+    fn dot(a: Self::Point, b: Self::Point) -> f32;
+
+    /// Construct a point from its coordinates
+    fn from_coords(coords: &[f32]) -> Self::Point;
+
+    /// Rotate a point around the origin
+    fn rotate(rotation: Self::Rotation, point: Self::Point) -> Self::Point;
+
+    /// Estimate the visible area of a volume from its mean width
+    fn area_from_width(mean_width: f32) -> f32;
+
+    /// Get the morton code for a point
+    fn morton_code(point: Self::Point) -> u64;
+}
+
+const INV_SQRT_3: f32 = 0.577_350_26;
+
+/// The axes pointing to the corners of a cube
+const CORNER_AXES: [Vec3A; 4] = [
+    Vec3A::new(INV_SQRT_3, INV_SQRT_3, INV_SQRT_3),
+    Vec3A::new(INV_SQRT_3, INV_SQRT_3, -INV_SQRT_3),
+    Vec3A::new(INV_SQRT_3, -INV_SQRT_3, INV_SQRT_3),
+    Vec3A::new(INV_SQRT_3, -INV_SQRT_3, -INV_SQRT_3),
+];
+
+/// The axes pointing to the edges of a cube
+const EDGE_AXES: [Vec3A; 6] = [
+    Vec3A::new(FRAC_1_SQRT_2, FRAC_1_SQRT_2, 0.),
+    Vec3A::new(FRAC_1_SQRT_2, 0., FRAC_1_SQRT_2),
+    Vec3A::new(0., FRAC_1_SQRT_2, FRAC_1_SQRT_2),
+    Vec3A::new(FRAC_1_SQRT_2, -FRAC_1_SQRT_2, 0.),
+    Vec3A::new(FRAC_1_SQRT_2, 0., -FRAC_1_SQRT_2),
+    Vec3A::new(0., FRAC_1_SQRT_2, -FRAC_1_SQRT_2),
+];
+
+impl KDopAxes<4> for KDop<4> {
+    type Point = Vec2;
+    type Rotation = Rot2;
+
+    const DIMENSIONS: usize = 2;
+    const AXES: [Vec2; 4] = [
+        Vec2::X,
+        Vec2::Y,
+        Vec2::new(FRAC_1_SQRT_2, FRAC_1_SQRT_2),
+        Vec2::new(FRAC_1_SQRT_2, -FRAC_1_SQRT_2),
+    ];
+
+    #[inline(always)]
+    fn dot(a: Vec2, b: Vec2) -> f32 {
+        a.dot(b)
+    }
+
+    #[inline(always)]
+    fn from_coords(coords: &[f32]) -> Vec2 {
+        Vec2::new(coords[0], coords[1])
+    }
+
+    #[inline(always)]
+    fn rotate(rotation: Rot2, point: Vec2) -> Vec2 {
+        rotation * point
+    }
+
+    #[inline(always)]
+    fn area_from_width(mean_width: f32) -> f32 {
+        // The area of a circle with the same mean width
+        PI / 4. * mean_width * mean_width
+    }
+
+    #[inline(always)]
+    fn morton_code(point: Vec2) -> u64 {
+        morton_encode_2d(
+            morton_cell(point.x, MORTON_BITS_2D),
+            morton_cell(point.y, MORTON_BITS_2D),
+        )
+    }
+}
+
+macro_rules! impl_kdop_axes_3d {
+    ($n:literal, [$($axes:expr),*]) => {
+        impl KDopAxes<$n> for KDop<$n> {
+            type Point = Vec3A;
+            type Rotation = Quat;
+
+            const DIMENSIONS: usize = 3;
+            const AXES: [Vec3A; $n] = [Vec3A::X, Vec3A::Y, Vec3A::Z, $($axes),*];
+
+            #[inline(always)]
+            fn dot(a: Vec3A, b: Vec3A) -> f32 {
+                a.dot(b)
+            }
+
+            #[inline(always)]
+            fn from_coords(coords: &[f32]) -> Vec3A {
+                Vec3A::new(coords[0], coords[1], coords[2])
+            }
+
+            #[inline(always)]
+            fn rotate(rotation: Quat, point: Vec3A) -> Vec3A {
+                rotation * point
+            }
+
+            #[inline(always)]
+            fn area_from_width(mean_width: f32) -> f32 {
+                // The surface area of a sphere with the same mean width
+                PI * mean_width * mean_width
+            }
+
+            #[inline(always)]
+            fn morton_code(point: Vec3A) -> u64 {
+                morton_encode(
+                    morton_cell(point.x, MORTON_BITS_3D),
+                    morton_cell(point.y, MORTON_BITS_3D),
+                    morton_cell(point.z, MORTON_BITS_3D),
+                )
+            }
+        }
+    };
+}
+
+impl_kdop_axes_3d!(
+    7,
+    [
+        CORNER_AXES[0],
+        CORNER_AXES[1],
+        CORNER_AXES[2],
+        CORNER_AXES[3]
+    ]
+);
+impl_kdop_axes_3d!(
+    9,
+    [
+        EDGE_AXES[0],
+        EDGE_AXES[1],
+        EDGE_AXES[2],
+        EDGE_AXES[3],
+        EDGE_AXES[4],
+        EDGE_AXES[5]
+    ]
+);
+impl_kdop_axes_3d!(
+    13,
+    [
+        CORNER_AXES[0],
+        CORNER_AXES[1],
+        CORNER_AXES[2],
+        CORNER_AXES[3],
+        EDGE_AXES[0],
+        EDGE_AXES[1],
+        EDGE_AXES[2],
+        EDGE_AXES[3],
+        EDGE_AXES[4],
+        EDGE_AXES[5]
+    ]
+);
+
+impl<const N: usize> KDop<N>
+where
+    Self: KDopAxes<N>,
+{
+    /// An empty k-DOP, merging anything into it results in the other volume
+    pub const EMPTY: Self = Self {
+        min: [f32::INFINITY; N],
+        max: [-f32::INFINITY; N],
+    };
+
+    /// Construct the tightest k-DOP containing all points
+    pub fn from_points(
+        points: impl IntoIterator<Item = impl Into<<Self as KDopAxes<N>>::Point>>,
+    ) -> Self {
+        let mut kdop = Self::EMPTY;
+        for point in points {
+            kdop.add_point(point.into());
+        }
+        kdop
+    }
+
+    /// Grow the k-DOP to contain the point
+    #[inline(always)]
+    pub fn add_point(&mut self, point: <Self as KDopAxes<N>>::Point) {
+        for (i, axis) in Self::AXES.into_iter().enumerate() {
+            let projected = Self::dot(axis, point);
+            self.min[i] = self.min[i].min(projected);
+            self.max[i] = self.max[i].max(projected);
+        }
+    }
+
+    /// Get the corners of the axis-aligned box made up by the coordinate axes
+    fn aabb_corners(&self) -> impl Iterator<Item = <Self as KDopAxes<N>>::Point> + '_ {
+        (0..1 << Self::DIMENSIONS).map(|corner: usize| {
+            let mut coords = [0.; 3];
+            for (axis, coord) in coords.iter_mut().enumerate().take(Self::DIMENSIONS) {
+                *coord = if corner & (1 << axis) == 0 {
+                    self.min[axis]
+                } else {
+                    self.max[axis]
+                };
+            }
+            Self::from_coords(&coords)
+        })
+    }
+
+    /// Get the distance of an intersection with a ray, if any
+    #[inline(always)]
+    fn ray_intersection_at(
+        &self,
+        origin: <Self as KDopAxes<N>>::Point,
+        direction: <Self as KDopAxes<N>>::Point,
+        max: f32,
+    ) -> Option<f32> {
+        let mut entry = 0f32;
+        let mut exit = max;
+        for (i, axis) in Self::AXES.into_iter().enumerate() {
+            let origin = Self::dot(axis, origin);
+            let direction = Self::dot(axis, direction);
+            if direction == 0. {
+                if origin < self.min[i] || origin > self.max[i] {
+                    return None;
+                }
+                continue;
+            }
+
+            let recip = direction.recip();
+            let t1 = (self.min[i] - origin) * recip;
+            let t2 = (self.max[i] - origin) * recip;
+            entry = entry.max(t1.min(t2));
+            exit = exit.min(t1.max(t2));
+        }
+
+        (entry <= exit).then_some(entry)
+    }
+
+    /// Check if the point's projection on every axis is within the slabs, after growing them by
+    /// `radius`
+    #[inline(always)]
+    fn overlaps_point(&self, point: <Self as KDopAxes<N>>::Point, radius: f32) -> bool {
+        Self::AXES.into_iter().enumerate().all(|(i, axis)| {
+            let projected = Self::dot(axis, point);
+            projected >= self.min[i] - radius && projected <= self.max[i] + radius
+        })
+    }
+}
+
+impl<const N: usize> BoundingVolume for KDop<N>
+where
+    Self: KDopAxes<N>,
+{
+    type Translation = <Self as KDopAxes<N>>::Point;
+    type Rotation = <Self as KDopAxes<N>>::Rotation;
+    type HalfSize = [f32; N];
+
+    #[inline(always)]
+    fn center(&self) -> Self::Translation {
+        let mut coords = [0.; 3];
+        for (axis, coord) in coords.iter_mut().enumerate().take(Self::DIMENSIONS) {
+            *coord = (self.min[axis] + self.max[axis]) / 2.;
+        }
+        Self::from_coords(&coords)
+    }
+
+    /// Get the half width of each slab
+    #[inline(always)]
+    fn half_size(&self) -> Self::HalfSize {
+        core::array::from_fn(|i| (self.max[i] - self.min[i]) / 2.)
+    }
+
+    /// Estimate the visible area from the mean width of the slabs
+    #[inline(always)]
+    fn visible_area(&self) -> f32 {
+        let total_width = (0..N).map(|i| self.max[i] - self.min[i]).sum::<f32>();
+        Self::area_from_width(total_width / N as f32)
+    }
+
+    #[inline(always)]
+    fn contains(&self, other: &Self) -> bool {
+        (0..N).all(|i| other.min[i] >= self.min[i] && other.max[i] <= self.max[i])
+    }
+
+    #[inline(always)]
+    fn merge(&self, other: &Self) -> Self {
+        Self {
+            min: core::array::from_fn(|i| self.min[i].min(other.min[i])),
+            max: core::array::from_fn(|i| self.max[i].max(other.max[i])),
+        }
+    }
+
+    #[inline(always)]
+    fn grow(&self, amount: impl Into<Self::HalfSize>) -> Self {
+        let amount = amount.into();
+        Self {
+            min: core::array::from_fn(|i| self.min[i] - amount[i]),
+            max: core::array::from_fn(|i| self.max[i] + amount[i]),
+        }
+    }
+
+    #[inline(always)]
+    fn shrink(&self, amount: impl Into<Self::HalfSize>) -> Self {
+        let amount = amount.into();
+        Self {
+            min: core::array::from_fn(|i| self.min[i] + amount[i]),
+            max: core::array::from_fn(|i| self.max[i] - amount[i]),
+        }
+    }
+
+    #[inline(always)]
+    fn scale_around_center(&self, scale: impl Into<Self::HalfSize>) -> Self {
+        let scale = scale.into();
+        let center = |i: usize| (self.min[i] + self.max[i]) / 2.;
+        let half_size = self.half_size();
+        Self {
+            min: core::array::from_fn(|i| center(i) - half_size[i] * scale[i]),
+            max: core::array::from_fn(|i| center(i) + half_size[i] * scale[i]),
+        }
+    }
+
+    #[inline(always)]
+    fn translate_by(&mut self, translation: impl Into<Self::Translation>) {
+        let translation = translation.into();
+        for (i, axis) in Self::AXES.into_iter().enumerate() {
+            let offset = Self::dot(axis, translation);
+            self.min[i] += offset;
+            self.max[i] += offset;
+        }
+    }
+
+    /// Rotates the bounding volume around the origin by the given rotation.
+    ///
+    /// The result is the k-DOP containing the rotated AABB of the original volume, so it can be
+    /// significantly looser than the original.
+    fn rotate_by(&mut self, rotation: impl Into<Self::Rotation>) {
+        let rotation = rotation.into();
+        let mut rotated = Self::EMPTY;
+        for corner in self.aabb_corners() {
+            rotated.add_point(Self::rotate(rotation, corner));
+        }
+        *self = rotated;
+    }
+}
+
+impl<const N: usize> BvhVolume for KDop<N>
+where
+    Self: KDopAxes<N>,
+{
+    const INFINITY: Self = Self {
+        min: [-f32::INFINITY; N],
+        max: [f32::INFINITY; N],
+    };
+
+    #[inline(always)]
+    fn morton_code(&self) -> u64 {
+        <Self as KDopAxes<N>>::morton_code(self.center())
+    }
+}
+
+impl<const N: usize> From<Aabb2d> for KDop<N>
+where
+    Self: KDopAxes<N, Point = Vec2>,
+{
+    /// Project the box on each axis through the corners furthest along and against the axis
+    #[inline(always)]
+    fn from(aabb: Aabb2d) -> Self {
+        let mut kdop = Self::EMPTY;
+        for (i, axis) in Self::AXES.into_iter().enumerate() {
+            let positive = axis.cmpge(Vec2::ZERO);
+            kdop.min[i] = axis.dot(Vec2::select(positive, aabb.min, aabb.max));
+            kdop.max[i] = axis.dot(Vec2::select(positive, aabb.max, aabb.min));
+        }
+        kdop
+    }
+}
+
+impl<const N: usize> From<Aabb3d> for KDop<N>
+where
+    Self: KDopAxes<N, Point = Vec3A>,
+{
+    /// Project the box on each axis through the corners furthest along and against the axis
+    #[inline(always)]
+    fn from(aabb: Aabb3d) -> Self {
+        let mut kdop = Self::EMPTY;
+        for (i, axis) in Self::AXES.into_iter().enumerate() {
+            let positive = axis.cmpge(Vec3A::ZERO);
+            kdop.min[i] = axis.dot(Vec3A::select(positive, aabb.min, aabb.max));
+            kdop.max[i] = axis.dot(Vec3A::select(positive, aabb.max, aabb.min));
+        }
+        kdop
+    }
+}
+
+impl<const N: usize> IntersectsVolume<KDop<N>> for KDop<N>
+where
+    Self: KDopAxes<N>,
+{
+    #[inline(always)]
+    fn intersects(&self, volume: &KDop<N>) -> bool {
+        (0..N).all(|i| self.min[i] <= volume.max[i] && volume.min[i] <= self.max[i])
+    }
+}
+
+impl<const N: usize> IntersectsVolume<KDop<N>> for Aabb2d
+where
+    KDop<N>: KDopAxes<N, Point = Vec2>,
+{
+    #[inline(always)]
+    fn intersects(&self, volume: &KDop<N>) -> bool {
+        KDop::from(*self).intersects(volume)
+    }
+}
+
+impl<const N: usize> IntersectsVolume<KDop<N>> for Aabb3d
+where
+    KDop<N>: KDopAxes<N, Point = Vec3A>,
+{
+    #[inline(always)]
+    fn intersects(&self, volume: &KDop<N>) -> bool {
+        KDop::from(*self).intersects(volume)
+    }
+}
+
+impl<const N: usize> IntersectsVolume<KDop<N>> for BoundingCircle
+where
+    KDop<N>: KDopAxes<N, Point = Vec2>,
+{
+    /// A conservative test, checking the circle's extent along each of the axes
+    #[inline(always)]
+    fn intersects(&self, volume: &KDop<N>) -> bool {
+        volume.overlaps_point(self.center, self.radius())
+    }
+}
+
+impl<const N: usize> IntersectsVolume<KDop<N>> for BoundingSphere
+where
+    KDop<N>: KDopAxes<N, Point = Vec3A>,
+{
+    /// A conservative test, checking the sphere's extent along each of the axes
+    #[inline(always)]
+    fn intersects(&self, volume: &KDop<N>) -> bool {
+        volume.overlaps_point(self.center, self.radius())
+    }
+}
+
+impl<const N: usize> IntersectsVolume<KDop<N>> for RayCast2d
+where
+    KDop<N>: KDopAxes<N, Point = Vec2>,
+{
+    #[inline(always)]
+    fn intersects(&self, volume: &KDop<N>) -> bool {
+        volume
+            .ray_intersection_at(self.ray.origin, *self.ray.direction, self.max)
+            .is_some()
+    }
+}
+
+impl<const N: usize> IntersectsVolume<KDop<N>> for RayCast3d
+where
+    KDop<N>: KDopAxes<N, Point = Vec3A>,
+{
+    #[inline(always)]
+    fn intersects(&self, volume: &KDop<N>) -> bool {
+        volume
+            .ray_intersection_at(self.origin, *self.direction, self.max)
+            .is_some()
+    }
+}
+
+#[cfg(test)]
+use bevy_math::{BVec3A, Dir3A};
+
+#[test]
+fn test_kdop() {
+    // A thin diagonal rod, which fills only a small part of its AABB
+    let rod = Dop26::from_points([Vec3A::ZERO, Vec3A::splat(10.)]);
+    let bvh = BvhKDop::new(
+        2,
+        [
+            (0, rod),
+            (1, Dop26::from(Aabb3d::new(Vec3A::splat(20.), Vec3A::ONE))),
+        ],
+    );
+    let mut stack = bvh.create_stack();
+
+    let corner = Aabb3d::new(Vec3A::new(9., 1., 1.), Vec3A::ONE);
+    assert!(corner.intersects(&Aabb3d::new(Vec3A::splat(5.), Vec3A::splat(5.))));
+    assert_eq!(bvh.traverse(&mut stack, corner).count(), 0);
+
+    let middle = Aabb3d::new(Vec3A::splat(5.), Vec3A::splat(0.5));
+    assert_eq!(
+        bvh.traverse(&mut stack, middle)
+            .copied()
+            .collect::<Vec<_>>(),
+        [0]
+    );
+
+    let ray = RayCast3d::new(Vec3A::new(9., 1., 5.), Dir3A::Y, 8.);
+    assert_eq!(bvh.traverse(&mut stack, ray).count(), 0);
+    let ray = RayCast3d::new(Vec3A::new(5., -5., 5.), Dir3A::Y, 20.);
+    assert_eq!(
+        bvh.traverse(&mut stack, ray).copied().collect::<Vec<_>>(),
+        [0]
+    );
+
+    let sphere = BoundingSphere::new(Vec3A::splat(20.), 2.);
+    assert_eq!(
+        bvh.traverse(&mut stack, sphere)
+            .copied()
+            .collect::<Vec<_>>(),
+        [1]
+    );
+
+    let merged = rod.merge(&bvh.items().nth(1).unwrap().volume);
+    assert!(merged.contains(&rod));
+    assert!(merged
+        .translated_by(Vec3A::X)
+        .contains(&rod.translated_by(Vec3A::X)));
+
+    // Projecting a box directly gives the same slabs as projecting all of its corners
+    let aabb = Aabb3d {
+        min: Vec3A::new(-3., 1., 2.),
+        max: Vec3A::new(4., 1.5, 7.),
+    };
+    let corners = (0..8).map(|corner| {
+        let mask = BVec3A::new(corner & 1 != 0, corner & 2 != 0, corner & 4 != 0);
+        Vec3A::select(mask, aabb.max, aabb.min)
+    });
+    assert_eq!(Dop26::from(aabb), Dop26::from_points(corners));
+    let aabb = Aabb2d {
+        min: Vec2::new(-3., 1.),
+        max: Vec2::new(4., 1.5),
+    };
+    let corners = [
+        aabb.min,
+        Vec2::new(aabb.max.x, aabb.min.y),
+        Vec2::new(aabb.min.x, aabb.max.y),
+        aabb.max,
+    ];
+    assert_eq!(Dop8::from(aabb), Dop8::from_points(corners));
+}
@@ -0,0 +1,196 @@
+//! A module for level-of-detail queries, which cut through the BVH where nodes become small enough
+//! according to a [`LodMetric`] and return those nodes as clusters of items
+
+use crate::node::NodeId;
+use crate::traverse::Stack;
+use crate::{Bvh, BvhItem, BvhItemVolume, BvhVolume};
+
+use core::ops::Range;
+
+use bevy_math::{
+    bounding::{Aabb3d, BoundingSphere, BoundingVolume},
+    Vec3A,
+};
+
+/// A metric deciding where the [`Bvh::lod_cut`] stops descending
+pub trait LodMetric<Volume> {
+    /// Check if the volume is small enough to be returned as a single cluster
+    fn accept(&self, volume: &Volume) -> bool;
+}
+
+impl<Volume, F: Fn(&Volume) -> bool> LodMetric<Volume> for F {
+    #[inline(always)]
+    fn accept(&self, volume: &Volume) -> bool {
+        self(volume)
+    }
+}
+
+/// A [`LodMetric`] accepting volumes whose size relative to their distance from the viewer is
+/// below a threshold. Volumes containing the viewer are never accepted
+#[derive(Clone, Copy, Debug)]
+pub struct ProjectedSize {
+    /// The position of the viewer
+    pub viewer: Vec3A,
+    /// The largest accepted ratio between the radius of a volume and its distance to the viewer
+    pub threshold: f32,
+}
+
+impl ProjectedSize {
+    /// Create a new metric
+    pub fn new(viewer: impl Into<Vec3A>, threshold: f32) -> Self {
+        Self {
+            viewer: viewer.into(),
+            threshold,
+        }
+    }
+
+    /// Create a metric accepting volumes that cover at most `pixels` on the screen, for a
+    /// perspective camera with the vertical field of view `fov` in radians
+    pub fn from_screen(
+        viewer: impl Into<Vec3A>,
+        fov: f32,
+        screen_height: f32,
+        pixels: f32,
+    ) -> Self {
+        // The diameter covers 2 * radius / (2 * distance * tan(fov / 2)) of the screen
+        let threshold = pixels / screen_height * bevy_math::ops::tan(fov / 2.);
+        Self::new(viewer, threshold)
+    }
+
+    #[inline(always)]
+    fn accept_sphere(&self, center: Vec3A, radius: f32) -> bool {
+        let distance = self.viewer.distance(center);
+        distance > radius && radius <= self.threshold * distance
+    }
+}
+
+impl LodMetric<Aabb3d> for ProjectedSize {
+    #[inline(always)]
+    fn accept(&self, volume: &Aabb3d) -> bool {
+        self.accept_sphere(volume.center(), volume.half_size().length())
+    }
+}
+
+impl LodMetric<BoundingSphere> for ProjectedSize {
+    #[inline(always)]
+    fn accept(&self, volume: &BoundingSphere) -> bool {
+        self.accept_sphere(volume.center, volume.radius())
+    }
+}
+
+/// A node returned by the [`Bvh::lod_cut`], standing in for all the items below it
+#[derive(Debug)]
+pub struct Cluster<'a, Volume: BvhVolume, T, Item: BvhItemVolume<Volume> = Volume> {
+    /// The node of the cluster
+    pub node: NodeId,
+    /// The volume of the node
+    pub volume: &'a Volume,
+    /// The range of the items, as indices into [`Bvh::items`]
+    pub range: Range<usize>,
+    /// The items below the node
+    pub items: &'a [BvhItem<Item, T>],
+}
+
+impl<Volume: BvhVolume, T, Item: BvhItemVolume<Volume>> Bvh<Volume, T, Item> {
+    /// Get the nodes where the tree gets cut by the [`LodMetric`]. Nodes are descended until the
+    /// metric accepts them, or until they are leaves. Every item is in exactly one cluster
+    pub fn lod_cut<'a, Metric: LodMetric<Volume>>(
+        &'a self,
+        stack: &'a mut Stack,
+        metric: Metric,
+    ) -> LodCut<'a, Volume, T, Metric, Item> {
+        stack.clear();
+        if !self.nodes.is_empty() {
+            stack.push_front(0);
+        }
+
+        LodCut {
+            bvh: self,
+            metric,
+            stack,
+        }
+    }
+}
+
+/// An iterator over the clusters of a [`Bvh::lod_cut`]
+pub struct LodCut<
+    'a,
+    Volume: BvhVolume,
+    T,
+    Metric: LodMetric<Volume>,
+    Item: BvhItemVolume<Volume> = Volume,
+> {
+    bvh: &'a Bvh<Volume, T, Item>,
+    /// The metric used to cut the tree
+    pub metric: Metric,
+    stack: &'a mut Stack,
+}
+
+impl<'a, Volume: BvhVolume, T, Metric: LodMetric<Volume>, Item: BvhItemVolume<Volume>> Iterator
+    for LodCut<'a, Volume, T, Metric, Item>
+{
+    type Item = Cluster<'a, Volume, T, Item>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let index = self.stack.pop_front()?;
+            let node = &self.bvh.nodes[index as usize];
+            if node.count == 0 && !self.metric.accept(&node.volume) {
+                self.stack.push_front(node.start_index + 1);
+                self.stack.push_front(node.start_index);
+                continue;
+            }
+
+            let id = NodeId::new(index);
+            let range = self.bvh.item_range(id);
+            return Some(Cluster {
+                node: id,
+                volume: &node.volume,
+                items: &self.bvh.items[range.clone()],
+                range,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+use crate::{dim3::BvhAabb3d, random_boxes};
+
+#[test]
+fn test_lod_cut() {
+    let boxes = random_boxes(2000, 1000., 1.);
+    let bvh = BvhAabb3d::new(2000, boxes.into_iter().enumerate());
+    let mut stack = bvh.create_stack();
+
+    // Accepting nothing cuts at the leaves, accepting everything returns the root
+    let leaves = bvh.nodes().filter(|node| node.count > 0).count();
+    assert_eq!(bvh.lod_cut(&mut stack, |_: &Aabb3d| false).count(), leaves);
+    let root = bvh
+        .lod_cut(&mut stack, |_: &Aabb3d| true)
+        .collect::<Vec<_>>();
+    assert_eq!(root.len(), 1);
+    assert_eq!(root[0].range, 0..2000);
+
+    // The cut stops at the first accepted node on each path, covering every item once
+    let viewer = Vec3A::ZERO;
+    let metric = ProjectedSize::new(viewer, 0.1);
+    let mut next = 0;
+    let mut clusters = 0;
+    for cluster in bvh.lod_cut(&mut stack, metric) {
+        assert_eq!(cluster.range.start, next);
+        next = cluster.range.end;
+        clusters += 1;
+
+        assert_eq!(cluster.items.len(), cluster.range.len());
+        let node = bvh.node(cluster.node);
+        assert!(node.count > 0 || metric.accept(cluster.volume));
+        if let Some(parent) = bvh.parent(cluster.node) {
+            assert!(!metric.accept(&bvh.node(parent).volume));
+        }
+        for item in cluster.items {
+            assert!(cluster.volume.contains(&item.volume));
+        }
+    }
+    assert_eq!(next, 2000);
+    assert!(clusters > 1 && clusters < leaves);
+}
@@ -0,0 +1,83 @@
+//! A module for reporting how much memory the BVH uses
+
+use crate::{Bvh, BvhItemVolume, BvhVolume};
+
+use alloc::vec::Vec;
+use core::mem::{size_of, size_of_val};
+
+/// The heap memory used by a [`Bvh`], in bytes
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MemoryUsage {
+    /// The bytes used by the nodes
+    pub nodes: usize,
+    /// The bytes used by the items, including their payloads but not anything the payloads own
+    pub items: usize,
+    /// The bytes used by the parent and skip links
+    pub links: usize,
+    /// The bytes allocated but not in use, see [`Bvh::shrink_to_fit`]
+    pub unused: usize,
+}
+
+impl MemoryUsage {
+    /// Get the total number of bytes allocated
+    pub fn total(&self) -> usize {
+        self.nodes + self.items + self.links + self.unused
+    }
+}
+
+impl<Volume: BvhVolume, T, Item: BvhItemVolume<Volume>> Bvh<Volume, T, Item> {
+    /// Get the heap memory used by the BVH
+    pub fn memory_usage(&self) -> MemoryUsage {
+        MemoryUsage {
+            nodes: size_of_val(self.nodes.as_slice()),
+            items: size_of_val(self.items.as_slice()),
+            links: size_of_val(self.parents.as_slice()) + size_of_val(self.skip_links.as_slice()),
+            unused: unused(&self.nodes)
+                + unused(&self.items)
+                + unused(&self.parents)
+                + unused(&self.skip_links),
+        }
+    }
+
+    /// Free the memory that was allocated but isn't used
+    pub fn shrink_to_fit(&mut self) {
+        self.nodes.shrink_to_fit();
+        self.items.shrink_to_fit();
+        self.parents.shrink_to_fit();
+        self.skip_links.shrink_to_fit();
+    }
+}
+
+fn unused<V>(v: &Vec<V>) -> usize {
+    (v.capacity() - v.len()) * size_of::<V>()
+}
+
+#[cfg(test)]
+use crate::{
+    dim3::{Aabb3d, BvhAabb3d},
+    random_boxes, BvhItem, BvhNode,
+};
+
+#[test]
+fn test_memory_usage() {
+    let boxes = random_boxes(1000, 100., 1.);
+    let items = (0..1000u32).zip(boxes);
+    // Overestimate the number of items, like callers filtering an iterator might
+    let mut bvh = BvhAabb3d::new(2000, items);
+    let usage = bvh.memory_usage();
+    assert_eq!(usage.nodes, bvh.n_nodes() * size_of::<BvhNode<Aabb3d>>());
+    assert_eq!(usage.items, 1000 * size_of::<BvhItem<Aabb3d, u32>>());
+    assert_eq!(usage.links, bvh.n_nodes() * size_of::<u32>());
+    assert!(usage.unused > 0);
+
+    bvh.shrink_to_fit();
+    let shrunk = bvh.memory_usage();
+    assert_eq!(shrunk.unused, 0);
+    assert_eq!(shrunk.total(), usage.total() - usage.unused);
+
+    bvh.compute_skip_links();
+    let linked = bvh.memory_usage();
+    assert_eq!(linked.links, 2 * shrunk.links);
+
+    assert_eq!(BvhAabb3d::<u32>::default().memory_usage().total(), 0);
+}
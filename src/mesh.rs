@@ -0,0 +1,776 @@
+//! A module with a triangle mesh acceleration structure, built on top of a [`BvhAabb3d`]
+
+use crate::dim3::BvhAabb3d;
+use crate::traverse::Stack;
+
+use alloc::{collections::BinaryHeap, vec, vec::Vec};
+use core::cmp::Reverse;
+
+use bevy_math::{
+    bounding::{Aabb3d, BoundingVolume, IntersectsVolume, RayCast3d},
+    ops, Dir3A, FloatOrd, Isometry3d, Vec3A,
+};
+
+/// A triangle mesh with a BVH over its triangles, used for exact queries against the triangles
+pub struct TriangleMesh {
+    vertices: Vec<Vec3A>,
+    triangles: Vec<[u32; 3]>,
+    bvh: BvhAabb3d<u32>,
+    watertight: bool,
+}
+
+/// The closest hit of a ray against a [`TriangleMesh`]
+#[derive(Clone, Copy, Debug)]
+pub struct MeshHit {
+    /// The index of the triangle that was hit
+    pub triangle: u32,
+    /// The distance along the ray
+    pub distance: f32,
+    /// The barycentric coordinates of the hit, as weights for the three vertices of the triangle
+    pub barycentric: Vec3A,
+    /// The normal of the triangle, based on its winding order
+    pub normal: Dir3A,
+}
+
+/// The closest point on the surface of a [`TriangleMesh`]
+#[derive(Clone, Copy, Debug)]
+pub struct MeshClosestPoint {
+    /// The closest point on the surface
+    pub point: Vec3A,
+    /// The index of the triangle the point is on
+    pub triangle: u32,
+    /// The distance from the query point
+    pub distance: f32,
+}
+
+/// A pair of intersecting triangles between two [`TriangleMesh`]es
+#[derive(Clone, Copy, Debug)]
+pub struct TriangleIntersection {
+    /// The index of the triangle in the first mesh
+    pub a: u32,
+    /// The index of the triangle in the second mesh
+    pub b: u32,
+    /// The segment where the triangles intersect, in the space of the first mesh. For coplanar
+    /// triangles the overlap is an area, and the segment spans the two corners of the overlap that
+    /// are furthest apart
+    pub segment: [Vec3A; 2],
+    /// Whether the triangles lie in the same plane
+    pub coplanar: bool,
+}
+
+/// The result of a point containment test against a [`TriangleMesh`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Containment {
+    /// The point is inside the mesh, or on its surface
+    Inside,
+    /// The point is outside the mesh
+    Outside,
+    /// The mesh is not watertight, so it has no well defined inside
+    NotWatertight,
+}
+
+/// The directions used for ray parity tests. They avoid the axes and diagonals to make it
+/// unlikely that rays graze the edges of axis-aligned geometry.
+const PARITY_DIRECTIONS: [[f32; 3]; 5] = [
+    [1., 2., 3.],
+    [-3., 1., 2.],
+    [2., -3., 1.],
+    [-1., -2., 3.],
+    [3., 2., -1.],
+];
+
+/// The tolerance on barycentric coordinates under which a ray is considered to hit an edge
+const EDGE_TOLERANCE: f32 = 1e-5;
+
+impl TriangleMesh {
+    /// Construct a triangle mesh from a vertex buffer and an index buffer, where every 3 indices
+    /// form a triangle
+    pub fn new(vertices: impl IntoIterator<Item = impl Into<Vec3A>>, indices: &[u32]) -> Self {
+        debug_assert_eq!(indices.len() % 3, 0);
+
+        let vertices = vertices.into_iter().map(Into::into).collect::<Vec<_>>();
+        let triangles = indices
+            .chunks_exact(3)
+            .map(|t| [t[0], t[1], t[2]])
+            .collect::<Vec<_>>();
+
+        let bvh = BvhAabb3d::new(
+            triangles.len(),
+            triangles.iter().enumerate().map(|(i, t)| {
+                let [a, b, c] = t.map(|v| vertices[v as usize]);
+                (
+                    i as u32,
+                    Aabb3d {
+                        min: a.min(b).min(c),
+                        max: a.max(b).max(c),
+                    },
+                )
+            }),
+        );
+
+        let watertight = is_watertight(&vertices, &triangles);
+
+        Self {
+            vertices,
+            triangles,
+            bvh,
+            watertight,
+        }
+    }
+
+    /// Check whether the mesh is watertight. This is the case if every edge is shared by
+    /// exactly two triangles that use it in opposite directions. Vertices at the same position are
+    /// treated as the same vertex.
+    pub fn is_watertight(&self) -> bool {
+        self.watertight
+    }
+
+    /// Get the vertices of the mesh
+    pub fn vertices(&self) -> &[Vec3A] {
+        &self.vertices
+    }
+
+    /// Get the triangles of the mesh, as indices into the vertices
+    pub fn triangles(&self) -> &[[u32; 3]] {
+        &self.triangles
+    }
+
+    /// Get the positions of the vertices of a triangle
+    pub fn triangle(&self, index: u32) -> [Vec3A; 3] {
+        self.triangles[index as usize].map(|v| self.vertices[v as usize])
+    }
+
+    /// Get the BVH over the triangles of the mesh, the items are the triangle indices
+    pub fn bvh(&self) -> &BvhAabb3d<u32> {
+        &self.bvh
+    }
+
+    /// Create a stack with the right size for the mesh's BVH
+    pub fn create_stack(&self) -> Stack {
+        self.bvh.create_stack()
+    }
+
+    /// Find the closest triangle hit by the ray
+    pub fn cast_ray(&self, stack: &mut Stack, ray: RayCast3d) -> Option<MeshHit> {
+        let mut closest = None;
+        let mut traverser = self.bvh.traverse(stack, ray);
+        while let Some(&triangle) = traverser.next() {
+            let ray = &traverser.tester;
+            let Some((distance, u, v)) =
+                ray_triangle(ray.origin, *ray.direction, self.triangle(triangle))
+            else {
+                continue;
+            };
+            if distance > ray.max {
+                continue;
+            }
+
+            // Only nodes closer than the current hit still need to be visited
+            traverser.tester.max = distance;
+            closest = Some((triangle, distance, u, v));
+        }
+
+        closest.map(|(triangle, distance, u, v)| {
+            let [a, b, c] = self.triangle(triangle);
+            MeshHit {
+                triangle,
+                distance,
+                barycentric: Vec3A::new(1. - u - v, u, v),
+                normal: Dir3A::new_unchecked((b - a).cross(c - a).normalize()),
+            }
+        })
+    }
+
+    /// Find the closest point on the surface of the mesh, ignoring anything further away than
+    /// `max_distance`. Nodes are visited best-first, ordered by their distance to the point.
+    pub fn closest_point(
+        &self,
+        point: impl Into<Vec3A>,
+        max_distance: f32,
+    ) -> Option<MeshClosestPoint> {
+        let point = point.into();
+        let bvh = &self.bvh;
+        if bvh.items.is_empty() {
+            return None;
+        }
+
+        let mut best_distance_squared = max_distance * max_distance;
+        let mut closest = None;
+
+        let distance_squared = |aabb: &Aabb3d| aabb.closest_point(point).distance_squared(point);
+        let mut heap = BinaryHeap::new();
+        heap.push((Reverse(FloatOrd(distance_squared(&bvh.nodes[0].volume))), 0));
+        while let Some((Reverse(FloatOrd(node_distance)), index)) = heap.pop() {
+            if node_distance > best_distance_squared {
+                // Every remaining node is further away than the current best
+                break;
+            }
+
+            let node = &bvh.nodes[index as usize];
+            if node.count == 0 {
+                for child in [node.start_index, node.start_index + 1] {
+                    let child_distance = distance_squared(&bvh.nodes[child as usize].volume);
+                    if child_distance <= best_distance_squared {
+                        heap.push((Reverse(FloatOrd(child_distance)), child));
+                    }
+                }
+                continue;
+            }
+
+            let start = node.start_index as usize;
+            for item in &bvh.items[start..start + node.count as usize] {
+                let closest_point = closest_point_on_triangle(point, self.triangle(item.t));
+                let distance = closest_point.distance_squared(point);
+                if distance < best_distance_squared
+                    || (closest.is_none() && distance <= best_distance_squared)
+                {
+                    best_distance_squared = distance;
+                    closest = Some((closest_point, item.t));
+                }
+            }
+        }
+
+        closest.map(|(closest_point, triangle)| MeshClosestPoint {
+            point: closest_point,
+            triangle,
+            distance: ops::sqrt(best_distance_squared),
+        })
+    }
+
+    /// Check if the point is inside the mesh, using the parity of ray crossings.
+    ///
+    /// Rays that hit an edge or vertex are ambiguous, in which case a different direction is tried.
+    /// Points on the surface count as inside.
+    pub fn contains_point(&self, stack: &mut Stack, point: impl Into<Vec3A>) -> Containment {
+        if !self.watertight {
+            return Containment::NotWatertight;
+        }
+        if self.bvh.items.is_empty() {
+            return Containment::Outside;
+        }
+
+        let point = point.into();
+        let root = &self.bvh.nodes[0].volume;
+        let surface_tolerance = (root.max - root.min).max_element() * 1e-6;
+
+        let mut inside_votes = 0;
+        for direction in PARITY_DIRECTIONS {
+            let direction = Vec3A::from_array(direction).normalize();
+            let ray = RayCast3d::new(point, Dir3A::new_unchecked(direction), f32::INFINITY);
+
+            let mut inside = false;
+            let mut ambiguous = false;
+            for &triangle in self.bvh.traverse(stack, ray) {
+                let Some((distance, u, v)) =
+                    ray_triangle(point, direction, self.triangle(triangle))
+                else {
+                    continue;
+                };
+                if distance <= surface_tolerance {
+                    return Containment::Inside;
+                }
+                if u < EDGE_TOLERANCE || v < EDGE_TOLERANCE || u + v > 1. - EDGE_TOLERANCE {
+                    ambiguous = true;
+                }
+                inside = !inside;
+            }
+
+            if !ambiguous {
+                return if inside {
+                    Containment::Inside
+                } else {
+                    Containment::Outside
+                };
+            }
+            if inside {
+                inside_votes += 1;
+            }
+        }
+
+        // Every direction was ambiguous, so we go with the majority
+        if inside_votes * 2 > PARITY_DIRECTIONS.len() {
+            Containment::Inside
+        } else {
+            Containment::Outside
+        }
+    }
+}
+
+impl TriangleMesh {
+    /// Find all pairs of intersecting triangles between this mesh and another mesh
+    pub fn intersect_mesh(&self, other: &TriangleMesh) -> Vec<TriangleIntersection> {
+        self.intersect_mesh_with(other, Isometry3d::IDENTITY)
+    }
+
+    /// Find all pairs of intersecting triangles between this mesh and another mesh, where the
+    /// other mesh is placed relative to this one using the isometry.
+    ///
+    /// Both trees are descended simultaneously. Overlapping coplanar triangles are reported too,
+    /// with [`TriangleIntersection::coplanar`] set.
+    pub fn intersect_mesh_with(
+        &self,
+        other: &TriangleMesh,
+        isometry: impl Into<Isometry3d>,
+    ) -> Vec<TriangleIntersection> {
+        let isometry = isometry.into();
+        let mut intersections = Vec::new();
+        if self.bvh.items.is_empty() || other.bvh.items.is_empty() {
+            return intersections;
+        }
+
+        let (a_bvh, b_bvh) = (&self.bvh, &other.bvh);
+        let transformed =
+            |volume: &Aabb3d| volume.transformed_by(isometry.translation, isometry.rotation);
+
+        let mut stack = vec![(0u32, 0u32)];
+        while let Some((a_index, b_index)) = stack.pop() {
+            let a = &a_bvh.nodes[a_index as usize];
+            let b = &b_bvh.nodes[b_index as usize];
+            let b_volume = transformed(&b.volume);
+            if !a.volume.intersects(&b_volume) {
+                continue;
+            }
+
+            // Descend into the largest internal node, until both nodes are leaves
+            let descend_a = match (a.count == 0, b.count == 0) {
+                (true, true) => a.volume.visible_area() >= b_volume.visible_area(),
+                (a_internal, _) => a_internal,
+            };
+            if a.count == 0 && descend_a {
+                stack.push((a.start_index, b_index));
+                stack.push((a.start_index + 1, b_index));
+                continue;
+            }
+            if b.count == 0 {
+                stack.push((a_index, b.start_index));
+                stack.push((a_index, b.start_index + 1));
+                continue;
+            }
+
+            let a_items = &a_bvh.items[a.start_index as usize..(a.start_index + a.count) as usize];
+            let b_items = &b_bvh.items[b.start_index as usize..(b.start_index + b.count) as usize];
+            for b_item in b_items {
+                let b_volume = transformed(&b_item.volume);
+                let b_triangle = other.triangle(b_item.t).map(|v| isometry * v);
+                for a_item in a_items {
+                    if !a_item.volume.intersects(&b_volume) {
+                        continue;
+                    }
+                    if let Some((segment, coplanar)) =
+                        triangle_intersection(self.triangle(a_item.t), b_triangle)
+                    {
+                        intersections.push(TriangleIntersection {
+                            a: a_item.t,
+                            b: b_item.t,
+                            segment,
+                            coplanar,
+                        });
+                    }
+                }
+            }
+        }
+
+        intersections
+    }
+}
+
+/// Get the segment where two triangles intersect, based on Möller's interval overlap test, and
+/// whether the triangles are coplanar. Degenerate triangles never intersect.
+fn triangle_intersection(a: [Vec3A; 3], b: [Vec3A; 3]) -> Option<([Vec3A; 2], bool)> {
+    let a_normal = (a[1] - a[0]).cross(a[2] - a[0]);
+    let b_normal = (b[1] - b[0]).cross(b[2] - b[0]);
+    if a_normal == Vec3A::ZERO || b_normal == Vec3A::ZERO {
+        return None;
+    }
+
+    // Reject the pair if either triangle is entirely on one side of the other's plane
+    let a_distances = a.map(|v| b_normal.dot(v - b[0]));
+    let b_distances = b.map(|v| a_normal.dot(v - a[0]));
+    let one_sided = |d: [f32; 3]| d.iter().all(|&d| d > 0.) || d.iter().all(|&d| d < 0.);
+    if one_sided(a_distances) || one_sided(b_distances) {
+        return None;
+    }
+
+    // Parallel planes that passed the test above are the same plane
+    let direction = a_normal.cross(b_normal);
+    let sine_squared =
+        direction.length_squared() / (a_normal.length_squared() * b_normal.length_squared());
+    if sine_squared <= f32::EPSILON * f32::EPSILON {
+        return coplanar_overlap(a, b, a_normal).map(|segment| (segment, true));
+    }
+
+    // Both triangles cross the line where the planes meet, the overlap of those intervals on the
+    // line is the intersection
+    let (a_min, a_max) = plane_crossing(a, a_distances, direction)?;
+    let (b_min, b_max) = plane_crossing(b, b_distances, direction)?;
+    let min = if a_min.0 >= b_min.0 { a_min } else { b_min };
+    let max = if a_max.0 <= b_max.0 { a_max } else { b_max };
+    (min.0 <= max.0).then_some(([min.1, max.1], false))
+}
+
+/// Get the overlap of two coplanar triangles as the segment between its two corners that are
+/// furthest apart. The overlap is found by clipping the first triangle by each edge of the second
+fn coplanar_overlap(a: [Vec3A; 3], b: [Vec3A; 3], normal: Vec3A) -> Option<[Vec3A; 2]> {
+    // Clipping a convex polygon adds at most one corner, so 6 corners are enough for a triangle
+    let mut corners = [Vec3A::ZERO; 6];
+    corners[..3].copy_from_slice(&a);
+    let mut len = 3;
+
+    let winding = normal.dot((b[1] - b[0]).cross(b[2] - b[0])).signum();
+    for i in 0..3 {
+        let (start, edge) = (b[i], b[(i + 1) % 3] - b[i]);
+        let inside = |p: Vec3A| normal.dot(edge.cross(p - start)) * winding;
+
+        let (clipped, clipped_len) = (corners, len);
+        len = 0;
+        let mut push = |p: Vec3A| {
+            if len < corners.len() {
+                corners[len] = p;
+                len += 1;
+            }
+        };
+        for j in 0..clipped_len {
+            let (p, q) = (clipped[j], clipped[(j + 1) % clipped_len]);
+            let (dp, dq) = (inside(p), inside(q));
+            if dp >= 0. {
+                push(p);
+            }
+            if (dp < 0. && dq > 0.) || (dp > 0. && dq < 0.) {
+                push(p + (q - p) * (dp / (dp - dq)));
+            }
+        }
+        if len == 0 {
+            return None;
+        }
+    }
+
+    let mut segment = [corners[0]; 2];
+    let mut longest = 0.;
+    for i in 0..len {
+        for j in i + 1..len {
+            let length = corners[i].distance_squared(corners[j]);
+            if length > longest {
+                longest = length;
+                segment = [corners[i], corners[j]];
+            }
+        }
+    }
+    Some(segment)
+}
+
+/// Get the extremes of where a triangle crosses a plane, given the distance of each vertex to the
+/// plane, as positions along the direction
+fn plane_crossing(
+    triangle: [Vec3A; 3],
+    distances: [f32; 3],
+    direction: Vec3A,
+) -> Option<((f32, Vec3A), (f32, Vec3A))> {
+    let mut range: Option<((f32, Vec3A), (f32, Vec3A))> = None;
+    let mut add = |point: Vec3A| {
+        let t = direction.dot(point);
+        range = Some(match range {
+            None => ((t, point), (t, point)),
+            Some((min, max)) => (
+                if t < min.0 { (t, point) } else { min },
+                if t > max.0 { (t, point) } else { max },
+            ),
+        });
+    };
+
+    for i in 0..3 {
+        let j = (i + 1) % 3;
+        let (di, dj) = (distances[i], distances[j]);
+        if di == 0. {
+            add(triangle[i]);
+        }
+        if (di < 0. && dj > 0.) || (di > 0. && dj < 0.) {
+            add(triangle[i] + (triangle[j] - triangle[i]) * (di / (di - dj)));
+        }
+    }
+
+    range
+}
+
+/// Check if every edge of the mesh is shared by exactly two triangles with opposite winding
+fn is_watertight(vertices: &[Vec3A], triangles: &[[u32; 3]]) -> bool {
+    // Weld vertices at the same position, so split vertices (for example for UVs or normals)
+    // don't open up the mesh
+    let key = |v: u32| {
+        (vertices[v as usize] + Vec3A::ZERO)
+            .to_array()
+            .map(f32::to_bits)
+    };
+    let mut order = (0..vertices.len() as u32).collect::<Vec<_>>();
+    order.sort_unstable_by_key(|&v| key(v));
+    let mut canonical = vec![0; vertices.len()];
+    let mut first = 0;
+    for (i, &v) in order.iter().enumerate() {
+        if key(v) != key(order[first]) {
+            first = i;
+        }
+        canonical[v as usize] = order[first];
+    }
+
+    let mut edges = Vec::with_capacity(triangles.len() * 3);
+    for triangle in triangles {
+        let [a, b, c] = triangle.map(|v| canonical[v as usize]);
+        for edge in [(a, b), (b, c), (c, a)] {
+            if edge.0 != edge.1 {
+                edges.push(edge);
+            }
+        }
+    }
+    edges.sort_unstable();
+
+    // Every directed edge should be unique, and the opposite edge should exist
+    edges.windows(2).all(|pair| pair[0] != pair[1])
+        && edges
+            .iter()
+            .all(|&(a, b)| edges.binary_search(&(b, a)).is_ok())
+}
+
+/// Get the closest point on a triangle, as described in Real-Time Collision Detection (5.1.5)
+fn closest_point_on_triangle(p: Vec3A, [a, b, c]: [Vec3A; 3]) -> Vec3A {
+    let ab = b - a;
+    let ac = c - a;
+
+    // Check the vertex region of a
+    let ap = p - a;
+    let d1 = ab.dot(ap);
+    let d2 = ac.dot(ap);
+    if d1 <= 0. && d2 <= 0. {
+        return a;
+    }
+
+    // Check the vertex region of b
+    let bp = p - b;
+    let d3 = ab.dot(bp);
+    let d4 = ac.dot(bp);
+    if d3 >= 0. && d4 <= d3 {
+        return b;
+    }
+
+    // Check the edge region of ab
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0. && d1 >= 0. && d3 <= 0. {
+        return a + ab * (d1 / (d1 - d3));
+    }
+
+    // Check the vertex region of c
+    let cp = p - c;
+    let d5 = ab.dot(cp);
+    let d6 = ac.dot(cp);
+    if d6 >= 0. && d5 <= d6 {
+        return c;
+    }
+
+    // Check the edge region of ac
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0. && d2 >= 0. && d6 <= 0. {
+        return a + ac * (d2 / (d2 - d6));
+    }
+
+    // Check the edge region of bc
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0. && d4 - d3 >= 0. && d5 - d6 >= 0. {
+        return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+    }
+
+    // The point projects inside the face
+    let denom = (va + vb + vc).recip();
+    a + ab * (vb * denom) + ac * (vc * denom)
+}
+
+/// Intersect a ray with a triangle using the Möller–Trumbore algorithm. Returns the distance along
+/// the ray and the barycentric coordinates of the second and third vertex.
+#[inline(always)]
+fn ray_triangle(origin: Vec3A, direction: Vec3A, [a, b, c]: [Vec3A; 3]) -> Option<(f32, f32, f32)> {
+    let edge1 = b - a;
+    let edge2 = c - a;
+    let p = direction.cross(edge2);
+    let det = edge1.dot(p);
+    if det.abs() <= f32::EPSILON * edge1.length() * edge2.length() {
+        // The ray is parallel to the triangle, or the triangle is degenerate
+        return None;
+    }
+    let inv_det = det.recip();
+
+    let s = origin - a;
+    let u = s.dot(p) * inv_det;
+    if !(0. ..=1.).contains(&u) {
+        return None;
+    }
+
+    let q = s.cross(edge1);
+    let v = direction.dot(q) * inv_det;
+    if v < 0. || u + v > 1. {
+        return None;
+    }
+
+    let distance = edge2.dot(q) * inv_det;
+    (distance >= 0.).then_some((distance, u, v))
+}
+
+#[cfg(test)]
+use bevy_math::Vec3;
+
+#[cfg(test)]
+fn test_cube() -> TriangleMesh {
+    let vertices = (0..8).map(|i| {
+        Vec3::new(
+            if i & 1 == 0 { -1. } else { 1. },
+            if i & 2 == 0 { -1. } else { 1. },
+            if i & 4 == 0 { -1. } else { 1. },
+        )
+    });
+    #[rustfmt::skip]
+    let indices = [
+        0, 2, 1, 1, 2, 3, // -z
+        4, 5, 6, 5, 7, 6, // +z
+        0, 1, 4, 1, 5, 4, // -y
+        2, 6, 3, 3, 6, 7, // +y
+        0, 4, 2, 2, 4, 6, // -x
+        1, 3, 5, 3, 7, 5, // +x
+    ];
+    TriangleMesh::new(vertices, &indices)
+}
+
+#[test]
+fn test_contains_point() {
+    let mesh = test_cube();
+    assert!(mesh.is_watertight());
+    let mut stack = mesh.create_stack();
+
+    assert_eq!(
+        mesh.contains_point(&mut stack, Vec3A::ZERO),
+        Containment::Inside
+    );
+    assert_eq!(
+        mesh.contains_point(&mut stack, Vec3A::new(0.3, -0.9, 0.5)),
+        Containment::Inside
+    );
+    assert_eq!(
+        mesh.contains_point(&mut stack, Vec3A::new(0., 0., 1.)),
+        Containment::Inside
+    );
+    assert_eq!(
+        mesh.contains_point(&mut stack, Vec3A::new(1.5, 0., 0.)),
+        Containment::Outside
+    );
+    assert_eq!(
+        mesh.contains_point(&mut stack, Vec3A::new(-2., -2., -2.)),
+        Containment::Outside
+    );
+
+    // Removing a triangle opens up the mesh
+    let open = TriangleMesh::new(
+        mesh.vertices().iter().copied(),
+        &mesh.triangles().as_flattened()[3..],
+    );
+    assert!(!open.is_watertight());
+    assert_eq!(
+        open.contains_point(&mut stack, Vec3A::ZERO),
+        Containment::NotWatertight
+    );
+}
+
+#[test]
+fn test_cast_ray() {
+    let mesh = test_cube();
+    let mut stack = mesh.create_stack();
+
+    let ray = RayCast3d::new(Vec3A::new(0.5, 0.25, 5.), Dir3A::NEG_Z, 100.);
+    let hit = mesh.cast_ray(&mut stack, ray).unwrap();
+    assert!((hit.distance - 4.).abs() < 1e-5);
+    assert!(hit.triangle == 2 || hit.triangle == 3);
+    assert!(hit.normal.dot(Vec3A::Z) > 0.999);
+    let [a, b, c] = mesh.triangle(hit.triangle);
+    let point = a * hit.barycentric.x + b * hit.barycentric.y + c * hit.barycentric.z;
+    assert!(point.distance(Vec3A::new(0.5, 0.25, 1.)) < 1e-5);
+
+    let ray = RayCast3d::new(Vec3A::new(0.5, 0.25, 5.), Dir3A::NEG_Z, 3.);
+    assert!(mesh.cast_ray(&mut stack, ray).is_none());
+    let ray = RayCast3d::new(Vec3A::new(3., 0., 5.), Dir3A::NEG_Z, 100.);
+    assert!(mesh.cast_ray(&mut stack, ray).is_none());
+}
+
+#[test]
+fn test_closest_point() {
+    let mesh = test_cube();
+
+    let closest = mesh.closest_point(Vec3A::new(0.2, 3., -0.4), 10.).unwrap();
+    assert!(closest.point.distance(Vec3A::new(0.2, 1., -0.4)) < 1e-5);
+    assert!((closest.distance - 2.).abs() < 1e-5);
+    assert!(closest.triangle == 6 || closest.triangle == 7);
+
+    // Points past a corner snap to the corner
+    let closest = mesh.closest_point(Vec3A::splat(2.), 10.).unwrap();
+    assert!(closest.point.distance(Vec3A::ONE) < 1e-5);
+
+    // Points inside the mesh find the nearest face
+    let closest = mesh.closest_point(Vec3A::new(0., 0., 0.8), 10.).unwrap();
+    assert!(closest.point.distance(Vec3A::new(0., 0., 1.)) < 1e-5);
+
+    assert!(mesh.closest_point(Vec3A::new(0., 5., 0.), 3.9).is_none());
+}
+
+#[test]
+fn test_intersect_mesh() {
+    let a = TriangleMesh::new(
+        [Vec3::ZERO, Vec3::new(4., 0., 0.), Vec3::new(0., 4., 0.)],
+        &[0, 1, 2],
+    );
+    let b = TriangleMesh::new(
+        [
+            Vec3::new(1., 1., -1.),
+            Vec3::new(1., 1., 1.),
+            Vec3::new(-2., 1., 0.),
+        ],
+        &[0, 1, 2],
+    );
+    let intersections = a.intersect_mesh(&b);
+    assert_eq!(intersections.len(), 1);
+    let [start, end] = intersections[0].segment;
+    let (start, end) = (start.min(end), start.max(end));
+    assert!(start.distance(Vec3A::new(0., 1., 0.)) < 1e-5);
+    assert!(end.distance(Vec3A::new(1., 1., 0.)) < 1e-5);
+    assert!(!intersections[0].coplanar);
+
+    // Overlapping triangles in the same plane, the overlap is the triangle (1, 1), (3, 1), (1, 3)
+    let c = TriangleMesh::new(
+        [
+            Vec3::new(1., 1., 0.),
+            Vec3::new(5., 1., 0.),
+            Vec3::new(1., 5., 0.),
+        ],
+        &[0, 1, 2],
+    );
+    let intersections = a.intersect_mesh(&c);
+    assert_eq!(intersections.len(), 1);
+    assert!(intersections[0].coplanar);
+    let [start, end] = intersections[0].segment;
+    let (start, end) = if start.x > end.x {
+        (start, end)
+    } else {
+        (end, start)
+    };
+    assert!(start.distance(Vec3A::new(3., 1., 0.)) < 1e-5);
+    assert!(end.distance(Vec3A::new(1., 3., 0.)) < 1e-5);
+    assert!(a.intersect_mesh_with(&c, Vec3::new(4., 4., 0.)).is_empty());
+
+    let cube = test_cube();
+    assert!(cube
+        .intersect_mesh_with(&cube, Vec3::new(5., 0., 0.))
+        .is_empty());
+
+    let intersections = cube.intersect_mesh_with(&cube, Vec3::new(1., 0.5, 0.5));
+    assert!(!intersections.is_empty());
+    for intersection in intersections {
+        for point in intersection.segment {
+            // The segments lie on the surface of both cubes
+            assert!(point.abs().max_element() < 1. + 1e-5);
+            let moved = point - Vec3A::new(1., 0.5, 0.5);
+            assert!(moved.abs().max_element() < 1. + 1e-5);
+        }
+    }
+}
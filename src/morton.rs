@@ -1,26 +1,126 @@
-fn split(mut x: usize, log_bits: usize) -> usize {
-    let bit_count = 1 << log_bits;
-    let mut mask = (1 << bit_count) - 1;
-    x &= mask;
-    let mut n = 1 << (log_bits);
-    for _ in (1..=(log_bits)).rev() {
-        mask = (mask | (mask << n)) & !(mask << (n / 2));
-        x = (x | (x << n)) & mask;
-        n >>= 1;
+/// The number of bits per axis in a 2D morton code
+pub const MORTON_BITS_2D: u32 = 32;
+
+/// The number of bits per axis in a 3D morton code
+pub const MORTON_BITS_3D: u32 = 21;
+
+/// Spread the lowest 32 bits of x so there is a zero bit between each of them
+fn split_2(x: u64) -> u64 {
+    let mut x = x & 0xffff_ffff;
+    x = (x | (x << 16)) & 0x0000_ffff_0000_ffff;
+    x = (x | (x << 8)) & 0x00ff_00ff_00ff_00ff;
+    x = (x | (x << 4)) & 0x0f0f_0f0f_0f0f_0f0f;
+    x = (x | (x << 2)) & 0x3333_3333_3333_3333;
+    (x | (x << 1)) & 0x5555_5555_5555_5555
+}
+
+/// Spread the lowest 21 bits of x so there are two zero bits between each of them
+fn split_3(x: u64) -> u64 {
+    let mut x = x & 0x1f_ffff;
+    x = (x | (x << 32)) & 0x001f_0000_0000_ffff;
+    x = (x | (x << 16)) & 0x001f_0000_ff00_00ff;
+    x = (x | (x << 8)) & 0x100f_00f0_0f00_f00f;
+    x = (x | (x << 4)) & 0x10c3_0c30_c30c_30c3;
+    (x | (x << 2)) & 0x1249_2492_4924_9249
+}
+
+/// Interleave the lowest [`MORTON_BITS_2D`] bits of each coordinate
+pub fn morton_encode_2d(x: u64, y: u64) -> u64 {
+    split_2(x) | (split_2(y) << 1)
+}
+
+/// Interleave the lowest [`MORTON_BITS_3D`] bits of each coordinate
+pub fn morton_encode(x: u64, y: u64, z: u64) -> u64 {
+    split_3(x) | (split_3(y) << 1) | (split_3(z) << 2)
+}
+
+/// Interleave the bits of 1 to 6 coordinates, using the lowest `64 / D` bits of each
+pub fn morton_encode_n<const D: usize>(coords: [u64; D]) -> u64 {
+    debug_assert!((1..=6).contains(&D));
+    let bits = 64 / D;
+    let mut code = 0;
+    for bit in 0..bits {
+        for (axis, coord) in coords.iter().enumerate() {
+            code |= ((coord >> bit) & 1) << (bit * D + axis);
+        }
+    }
+    code
+}
+
+/// Map a coordinate to one of the `2^bits` unit-sized cells, centered around the origin.
+/// Coordinates outside the range end up in the outermost cells
+#[inline(always)]
+pub fn morton_cell(x: f32, bits: u32) -> u64 {
+    let max = u64::MAX >> (64 - bits);
+    (x as f64 + (1u64 << (bits - 1)) as f64).clamp(0., max as f64) as u64
+}
+
+/// Get the position of the coordinates along a hilbert curve, using the lowest `bits` bits of each.
+///
+/// Uses Skilling's transform, turning the coordinates into the transposed hilbert index which is
+/// then interleaved like a morton code.
+pub fn hilbert_encode<const D: usize>(mut coords: [u64; D], bits: u32) -> u64 {
+    debug_assert!((1..=6).contains(&D) && bits as usize <= 64 / D);
+    let m = 1 << (bits - 1);
+
+    // Undo the excess work of the inverse transform
+    let mut q = m;
+    while q > 1 {
+        let p = q - 1;
+        for i in 0..D {
+            if coords[i] & q != 0 {
+                coords[0] ^= p;
+            } else {
+                let t = (coords[0] ^ coords[i]) & p;
+                coords[0] ^= t;
+                coords[i] ^= t;
+            }
+        }
+        q >>= 1;
+    }
+
+    // Gray encode
+    for i in 1..D {
+        coords[i] ^= coords[i - 1];
     }
-    x
+    let mut t = 0;
+    let mut q = m;
+    while q > 1 {
+        if coords[D - 1] & q != 0 {
+            t ^= q - 1;
+        }
+        q >>= 1;
+    }
+    for coord in coords.iter_mut() {
+        *coord ^= t;
+    }
+
+    // The first coordinate holds the most significant bit of each group
+    coords.reverse();
+    morton_encode_n(coords)
 }
 
-pub fn morton_encode(x: usize, y: usize, z: usize, log_bits: usize) -> usize {
-    split(x, log_bits) | (split(y, log_bits) << 1) | (split(z, log_bits) << 2)
+/// Map a float to an integer with the same ordering, keeping only the `bits` most significant bits.
+/// Unlike [`morton_cell`] this never saturates, but cells grow with the distance to the origin.
+pub fn ordered_bits(x: f64, bits: u32) -> u64 {
+    let x = (x + 0.).to_bits();
+    let ordered = if x >> 63 == 1 { !x } else { x | (1 << 63) };
+    ordered >> (64 - bits)
 }
 
-pub const MORTON_CENTER: f32 = (u16::MAX / 2) as f32;
+/// Map a coordinate to one of the `2^bits` cells spread evenly over `min..=max`. Coordinates
+/// outside the range end up in the outermost cells
+#[inline(always)]
+pub fn bounded_cell(x: f64, min: f64, max: f64, bits: u32) -> u64 {
+    let max_cell = u64::MAX >> (64 - bits);
+    let scale = max_cell as f64 / (max - min);
+    ((x - min) * scale).clamp(0., max_cell as f64) as u64
+}
 
 #[test]
 fn test_morton_encode() {
     let (x, y, z) = (6.7, 19.3, 2.);
-    let morton = morton_encode(x as usize, y as usize, z as usize, 3);
+    let morton = morton_encode(x as u64, y as u64, z as u64);
     // 6 =  00110
     // 19 = 10011
     // 2 =  00010
@@ -28,9 +128,69 @@ fn test_morton_encode() {
     assert_eq!(morton, 0b010_000_001_111_010);
 
     let (x, y, z) = (6000, 3000, 1234);
-    let morton = morton_encode(x as usize, y as usize, z as usize, 4);
+    let morton = morton_encode(x, y, z);
     // 6000 = 1011101110000
     // 3000 = 0101110111000
     // 1234 = 0010011010010
     assert_eq!(morton, 0b001_010_101_011_011_110_101_011_111_010_000_100_000);
 }
+
+#[test]
+fn test_morton_encode_n() {
+    // Three dimensions match the fixed 3D encoding
+    assert_eq!(morton_encode_n([6, 19, 2]), morton_encode(6, 19, 2));
+
+    // 6 = 110, 3 = 011
+    // Bits should return as yx_yx_yx
+    assert_eq!(morton_encode_n([6, 3]), 0b01_11_10);
+    assert_eq!(morton_encode_2d(6, 3), 0b01_11_10);
+    // All 64 bits are used, on every platform
+    assert_eq!(morton_encode_2d(u32::MAX as u64, u32::MAX as u64), u64::MAX);
+    assert_eq!(morton_encode(1 << 20, 1 << 20, 1 << 20), 0b111 << 60);
+    assert_eq!(morton_encode_n([1234]), 1234);
+    assert_eq!(morton_encode_n([1, 0, 0, 0, 1, 1]), 0b110001);
+}
+
+#[test]
+fn test_hilbert_encode() {
+    // 0 = (0, 0), 1 = (0, 1), 2 = (1, 1), 3 = (1, 0)
+    assert_eq!(hilbert_encode([0, 0], 1), 0);
+    assert_eq!(hilbert_encode([0, 1], 1), 1);
+    assert_eq!(hilbert_encode([1, 1], 1), 2);
+    assert_eq!(hilbert_encode([1, 0], 1), 3);
+
+    // Every step along the curve moves to a neighboring cell
+    let mut cells = (0..8 * 8 * 8)
+        .map(|i| [i & 7, (i >> 3) & 7, i >> 6])
+        .collect::<Vec<_>>();
+    cells.sort_by_key(|cell| hilbert_encode(*cell, 3));
+    for pair in cells.windows(2) {
+        let distance = (0..3)
+            .map(|axis| pair[0][axis].abs_diff(pair[1][axis]))
+            .sum::<u64>();
+        assert_eq!(distance, 1);
+    }
+    let mut cells = (0..64 * 64)
+        .map(|i| [i & 63, i >> 6])
+        .collect::<Vec<_>>();
+    cells.sort_by_key(|cell| hilbert_encode(*cell, 6));
+    for pair in cells.windows(2) {
+        assert_eq!(
+            pair[0][0].abs_diff(pair[1][0]) + pair[0][1].abs_diff(pair[1][1]),
+            1
+        );
+    }
+}
+
+#[test]
+fn test_ordered_bits() {
+    let values = [
+        -1e9, -70_000., -1., -0., 0., 1e-3, 1., 40_000., 100_000., 1e9,
+    ];
+    for pair in values.windows(2) {
+        assert!(ordered_bits(pair[0], 21) <= ordered_bits(pair[1], 21));
+    }
+    // Values far outside the range of `morton_cell` still get distinct cells
+    assert!(ordered_bits(70_000., 21) < ordered_bits(100_000., 21));
+    assert!(ordered_bits(-100_000., 21) < ordered_bits(-70_000., 21));
+}
@@ -0,0 +1,136 @@
+//! A module for navigating the nodes of the BVH, without depending on how they are stored
+
+use crate::{Bvh, BvhItem, BvhItemVolume, BvhNode, BvhVolume};
+
+use core::ops::Range;
+
+/// The id of a node on the [`Bvh`]. Only valid for the BVH it was taken from
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeId(u32);
+
+impl NodeId {
+    pub(crate) fn new(index: u32) -> Self {
+        Self(index)
+    }
+
+    /// Get the index of the node, matching its position in [`Bvh::nodes`]
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl<Volume: BvhVolume, T, Item: BvhItemVolume<Volume>> Bvh<Volume, T, Item> {
+    /// Get the root node, or `None` if the BVH is empty
+    pub fn root(&self) -> Option<NodeId> {
+        (!self.nodes.is_empty()).then_some(NodeId(0))
+    }
+
+    /// Get a node
+    pub fn node(&self, id: NodeId) -> &BvhNode<Volume> {
+        &self.nodes[id.index()]
+    }
+
+    /// Check if the node holds items rather than other nodes
+    pub fn is_leaf(&self, id: NodeId) -> bool {
+        self.node(id).count > 0
+    }
+
+    /// Get the two children of a node, or `None` for a leaf
+    pub fn children(&self, id: NodeId) -> Option<[NodeId; 2]> {
+        let node = self.node(id);
+        (node.count == 0).then(|| [NodeId(node.start_index), NodeId(node.start_index + 1)])
+    }
+
+    /// Get the parent of a node, or `None` for the root
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        match self.parents[id.index()] {
+            u32::MAX => None,
+            parent => Some(NodeId(parent)),
+        }
+    }
+
+    /// Get the items of a leaf. Empty for other nodes
+    pub fn items_of(&self, id: NodeId) -> &[BvhItem<Item, T>] {
+        let node = self.node(id);
+        let start = node.start_index as usize;
+        match node.count {
+            0 => &[],
+            count => &self.items[start..start + count as usize],
+        }
+    }
+
+    /// Get the range of items below a node, as indices into [`Bvh::items`]. The items of each
+    /// subtree are stored next to each other
+    pub fn item_range(&self, id: NodeId) -> Range<usize> {
+        let (mut first, mut last) = (self.node(id), self.node(id));
+        while first.count == 0 {
+            first = &self.nodes[first.start_index as usize];
+        }
+        while last.count == 0 {
+            last = &self.nodes[last.start_index as usize + 1];
+        }
+        first.start_index as usize..(last.start_index + last.count) as usize
+    }
+
+    /// Get the number of edges between the node and the root
+    pub fn depth(&self, id: NodeId) -> usize {
+        let mut depth = 0;
+        let mut id = id;
+        while let Some(parent) = self.parent(id) {
+            id = parent;
+            depth += 1;
+        }
+        depth
+    }
+}
+
+#[cfg(test)]
+use crate::{
+    dim3::{BoundingVolume, BvhAabb3d},
+    random_boxes,
+};
+
+#[test]
+fn test_navigation() {
+    assert_eq!(BvhAabb3d::<usize>::default().root(), None);
+
+    let boxes = random_boxes(300, 100., 1.);
+    let bvh = BvhAabb3d::new(300, boxes.into_iter().enumerate());
+    let root = bvh.root().unwrap();
+    assert_eq!(bvh.parent(root), None);
+    assert_eq!(bvh.depth(root), 0);
+
+    // Every node and item is reached exactly once from the root
+    let mut found = Vec::new();
+    let mut nodes = 0;
+    let mut pending = vec![root];
+    while let Some(id) = pending.pop() {
+        nodes += 1;
+        let volume = &bvh.node(id).volume;
+        match bvh.children(id) {
+            Some(children) => {
+                assert!(!bvh.is_leaf(id) && bvh.items_of(id).is_empty());
+                let [left, right] = children.map(|child| bvh.item_range(child));
+                assert_eq!(left.end, right.start);
+                assert_eq!(bvh.item_range(id), left.start..right.end);
+                for child in children {
+                    assert_eq!(bvh.parent(child), Some(id));
+                    assert_eq!(bvh.depth(child), bvh.depth(id) + 1);
+                    assert!(volume.contains(&bvh.node(child).volume));
+                }
+                pending.extend(children);
+            }
+            None => {
+                assert!(bvh.is_leaf(id));
+                for item in bvh.items_of(id) {
+                    assert!(volume.contains(&item.volume));
+                    found.push(item.t);
+                }
+            }
+        }
+    }
+    found.sort();
+    assert_eq!(found, (0..300).collect::<Vec<_>>());
+    assert_eq!(nodes, bvh.n_nodes());
+    assert_eq!(bvh.item_range(root), 0..300);
+}
@@ -0,0 +1,550 @@
+//! A module with oriented bounding box volumes, which give much tighter bounds than axis-aligned
+//! boxes for long rotated objects
+
+use crate::morton::{morton_cell, morton_encode, morton_encode_2d, MORTON_BITS_2D, MORTON_BITS_3D};
+use crate::{BvhItemVolume, BvhVolume};
+
+use bevy_math::{
+    bounding::{
+        Aabb2d, Aabb3d, BoundingCircle, BoundingSphere, BoundingVolume, IntersectsVolume,
+        RayCast2d, RayCast3d,
+    },
+    Dir2, Dir3A, Mat3A, Quat, Rot2, Vec2, Vec3A,
+};
+
+/// A 2D oriented bounding box
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Obb2d {
+    /// The center of the box
+    pub center: Vec2,
+    /// The rotation of the box
+    pub rotation: Rot2,
+    /// The half size of the box along its own axes
+    pub half_size: Vec2,
+}
+
+impl Obb2d {
+    /// Construct an oriented bounding box from its center, rotation and half size
+    pub fn new(center: Vec2, rotation: Rot2, half_size: Vec2) -> Self {
+        debug_assert!(half_size.x >= 0. && half_size.y >= 0.);
+        Self {
+            center,
+            rotation,
+            half_size,
+        }
+    }
+
+    /// Get the axes of the box
+    #[inline(always)]
+    pub fn axes(&self) -> [Vec2; 2] {
+        [self.rotation * Vec2::X, self.rotation * Vec2::Y]
+    }
+
+    /// Transform a point into the local space of the box
+    #[inline(always)]
+    pub fn to_local(&self, point: Vec2) -> Vec2 {
+        self.rotation.inverse() * (point - self.center)
+    }
+
+    /// Get the half size of the box projected on each of the axes
+    #[inline(always)]
+    fn projected_half_size(&self, axes: [Vec2; 2]) -> Vec2 {
+        let [x, y] = self.axes();
+        let half_size = |axis: Vec2| {
+            self.half_size.x * axis.dot(x).abs() + self.half_size.y * axis.dot(y).abs()
+        };
+        Vec2::new(half_size(axes[0]), half_size(axes[1]))
+    }
+
+    /// Get the [`Aabb2d`] containing the box
+    pub fn aabb_2d(&self) -> Aabb2d {
+        Aabb2d::new(self.center, self.projected_half_size([Vec2::X, Vec2::Y]))
+    }
+
+    /// Get the distance of an intersection with a ray, if any
+    pub fn ray_intersection_at(&self, ray: &RayCast2d) -> Option<f32> {
+        let local = RayCast2d::new(
+            self.to_local(ray.ray.origin),
+            Dir2::new_unchecked(self.rotation.inverse() * *ray.ray.direction),
+            ray.max,
+        );
+        local.aabb_intersection_at(&Aabb2d {
+            min: -self.half_size,
+            max: self.half_size,
+        })
+    }
+}
+
+impl From<Aabb2d> for Obb2d {
+    fn from(aabb: Aabb2d) -> Self {
+        Self::new(aabb.center(), Rot2::IDENTITY, aabb.half_size())
+    }
+}
+
+impl BoundingVolume for Obb2d {
+    type Translation = Vec2;
+    type Rotation = Rot2;
+    type HalfSize = Vec2;
+
+    #[inline(always)]
+    fn center(&self) -> Self::Translation {
+        self.center
+    }
+
+    #[inline(always)]
+    fn half_size(&self) -> Self::HalfSize {
+        self.half_size
+    }
+
+    #[inline(always)]
+    fn visible_area(&self) -> f32 {
+        let b = self.half_size * 2.;
+        b.x * b.y
+    }
+
+    #[inline(always)]
+    fn contains(&self, other: &Self) -> bool {
+        let offset = self.to_local(other.center).abs();
+        let half_size = other.projected_half_size(self.axes());
+        (offset + half_size).cmple(self.half_size).all()
+    }
+
+    /// Merges two boxes into a box containing both. The rotation of the result is interpolated
+    /// between the two boxes, weighted by their area.
+    fn merge(&self, other: &Self) -> Self {
+        let rotation = self
+            .rotation
+            .slerp(other.rotation, merge_weight(self, other));
+        let axes = [rotation * Vec2::X, rotation * Vec2::Y];
+
+        let local = |obb: &Self| {
+            let center = Vec2::new(axes[0].dot(obb.center), axes[1].dot(obb.center));
+            let half_size = obb.projected_half_size(axes);
+            (center - half_size, center + half_size)
+        };
+        let (a_min, a_max) = local(self);
+        let (b_min, b_max) = local(other);
+        let (min, max) = (a_min.min(b_min), a_max.max(b_max));
+
+        Self::new(rotation * ((min + max) / 2.), rotation, (max - min) / 2.)
+    }
+
+    #[inline(always)]
+    fn grow(&self, amount: impl Into<Self::HalfSize>) -> Self {
+        Self::new(self.center, self.rotation, self.half_size + amount.into())
+    }
+
+    #[inline(always)]
+    fn shrink(&self, amount: impl Into<Self::HalfSize>) -> Self {
+        Self::new(self.center, self.rotation, self.half_size - amount.into())
+    }
+
+    #[inline(always)]
+    fn scale_around_center(&self, scale: impl Into<Self::HalfSize>) -> Self {
+        Self::new(self.center, self.rotation, self.half_size * scale.into())
+    }
+
+    #[inline(always)]
+    fn translate_by(&mut self, translation: impl Into<Self::Translation>) {
+        self.center += translation.into();
+    }
+
+    #[inline(always)]
+    fn rotate_by(&mut self, rotation: impl Into<Self::Rotation>) {
+        let rotation = rotation.into();
+        self.center = rotation * self.center;
+        self.rotation = (rotation * self.rotation).normalize();
+    }
+}
+
+impl BvhVolume for Obb2d {
+    const INFINITY: Self = Self {
+        center: Vec2::ZERO,
+        rotation: Rot2::IDENTITY,
+        half_size: Vec2::INFINITY,
+    };
+
+    #[inline(always)]
+    fn morton_code(&self) -> u64 {
+        let center = self.center;
+        morton_encode_2d(
+            morton_cell(center.x, MORTON_BITS_2D),
+            morton_cell(center.y, MORTON_BITS_2D),
+        )
+    }
+}
+
+impl BvhItemVolume<Aabb2d> for Obb2d {
+    #[inline(always)]
+    fn node_volume(&self) -> Aabb2d {
+        self.aabb_2d()
+    }
+}
+
+impl IntersectsVolume<Obb2d> for Obb2d {
+    #[inline(always)]
+    fn intersects(&self, volume: &Obb2d) -> bool {
+        let offset = volume.center - self.center;
+        self.axes().into_iter().chain(volume.axes()).all(|axis| {
+            let projected = |obb: &Obb2d| obb.projected_half_size([axis, axis]).x;
+            offset.dot(axis).abs() <= projected(self) + projected(volume)
+        })
+    }
+}
+
+impl IntersectsVolume<Aabb2d> for Obb2d {
+    #[inline(always)]
+    fn intersects(&self, volume: &Aabb2d) -> bool {
+        self.intersects(&Obb2d::from(*volume))
+    }
+}
+
+impl IntersectsVolume<Obb2d> for Aabb2d {
+    #[inline(always)]
+    fn intersects(&self, volume: &Obb2d) -> bool {
+        volume.intersects(self)
+    }
+}
+
+impl IntersectsVolume<BoundingCircle> for Obb2d {
+    #[inline(always)]
+    fn intersects(&self, volume: &BoundingCircle) -> bool {
+        let local = self.to_local(volume.center());
+        let closest = local.clamp(-self.half_size, self.half_size);
+        let radius = volume.radius();
+        closest.distance_squared(local) <= radius * radius
+    }
+}
+
+impl IntersectsVolume<Obb2d> for BoundingCircle {
+    #[inline(always)]
+    fn intersects(&self, volume: &Obb2d) -> bool {
+        volume.intersects(self)
+    }
+}
+
+impl IntersectsVolume<Obb2d> for RayCast2d {
+    #[inline(always)]
+    fn intersects(&self, volume: &Obb2d) -> bool {
+        volume.ray_intersection_at(self).is_some()
+    }
+}
+
+/// A 3D oriented bounding box
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Obb3d {
+    /// The center of the box
+    pub center: Vec3A,
+    /// The rotation of the box
+    pub rotation: Quat,
+    /// The half size of the box along its own axes
+    pub half_size: Vec3A,
+}
+
+impl Obb3d {
+    /// Construct an oriented bounding box from its center, rotation and half size
+    pub fn new(center: impl Into<Vec3A>, rotation: Quat, half_size: impl Into<Vec3A>) -> Self {
+        let half_size = half_size.into();
+        debug_assert!(half_size.cmpge(Vec3A::ZERO).all());
+        Self {
+            center: center.into(),
+            rotation,
+            half_size,
+        }
+    }
+
+    /// Get the axes of the box, as the columns of a matrix
+    #[inline(always)]
+    pub fn axes(&self) -> Mat3A {
+        Mat3A::from_quat(self.rotation)
+    }
+
+    /// Transform a point into the local space of the box
+    #[inline(always)]
+    pub fn to_local(&self, point: impl Into<Vec3A>) -> Vec3A {
+        self.rotation.inverse() * (point.into() - self.center)
+    }
+
+    /// Get the half size of the box projected on each of the axes
+    #[inline(always)]
+    fn projected_half_size(&self, axes: Mat3A) -> Vec3A {
+        let relative = axes.transpose() * self.axes();
+        Mat3A::from_cols(
+            relative.x_axis.abs(),
+            relative.y_axis.abs(),
+            relative.z_axis.abs(),
+        ) * self.half_size
+    }
+
+    /// Get the [`Aabb3d`] containing the box
+    pub fn aabb_3d(&self) -> Aabb3d {
+        Aabb3d::new(self.center, self.projected_half_size(Mat3A::IDENTITY))
+    }
+
+    /// Get the distance of an intersection with a ray, if any
+    pub fn ray_intersection_at(&self, ray: &RayCast3d) -> Option<f32> {
+        let local = RayCast3d::new(
+            self.to_local(ray.origin),
+            Dir3A::new_unchecked(self.rotation.inverse() * *ray.direction),
+            ray.max,
+        );
+        local.aabb_intersection_at(&Aabb3d {
+            min: -self.half_size,
+            max: self.half_size,
+        })
+    }
+}
+
+impl From<Aabb3d> for Obb3d {
+    fn from(aabb: Aabb3d) -> Self {
+        Self::new(aabb.center(), Quat::IDENTITY, aabb.half_size())
+    }
+}
+
+impl BoundingVolume for Obb3d {
+    type Translation = Vec3A;
+    type Rotation = Quat;
+    type HalfSize = Vec3A;
+
+    #[inline(always)]
+    fn center(&self) -> Self::Translation {
+        self.center
+    }
+
+    #[inline(always)]
+    fn half_size(&self) -> Self::HalfSize {
+        self.half_size
+    }
+
+    #[inline(always)]
+    fn visible_area(&self) -> f32 {
+        let b = self.half_size * 2.;
+        b.x * (b.y + b.z) + b.y * b.z
+    }
+
+    #[inline(always)]
+    fn contains(&self, other: &Self) -> bool {
+        let offset = self.to_local(other.center).abs();
+        let half_size = other.projected_half_size(self.axes());
+        (offset + half_size).cmple(self.half_size).all()
+    }
+
+    /// Merges two boxes into a box containing both. The rotation of the result is interpolated
+    /// between the two boxes, weighted by their area.
+    fn merge(&self, other: &Self) -> Self {
+        let rotation = self
+            .rotation
+            .slerp(other.rotation, merge_weight(self, other))
+            .normalize();
+        let axes = Mat3A::from_quat(rotation);
+
+        let local = |obb: &Self| {
+            let center = axes.transpose() * obb.center;
+            let half_size = obb.projected_half_size(axes);
+            (center - half_size, center + half_size)
+        };
+        let (a_min, a_max) = local(self);
+        let (b_min, b_max) = local(other);
+        let (min, max) = (a_min.min(b_min), a_max.max(b_max));
+
+        Self::new(axes * ((min + max) / 2.), rotation, (max - min) / 2.)
+    }
+
+    #[inline(always)]
+    fn grow(&self, amount: impl Into<Self::HalfSize>) -> Self {
+        Self::new(self.center, self.rotation, self.half_size + amount.into())
+    }
+
+    #[inline(always)]
+    fn shrink(&self, amount: impl Into<Self::HalfSize>) -> Self {
+        Self::new(self.center, self.rotation, self.half_size - amount.into())
+    }
+
+    #[inline(always)]
+    fn scale_around_center(&self, scale: impl Into<Self::HalfSize>) -> Self {
+        Self::new(self.center, self.rotation, self.half_size * scale.into())
+    }
+
+    #[inline(always)]
+    fn translate_by(&mut self, translation: impl Into<Self::Translation>) {
+        self.center += translation.into();
+    }
+
+    #[inline(always)]
+    fn rotate_by(&mut self, rotation: impl Into<Self::Rotation>) {
+        let rotation = rotation.into();
+        self.center = rotation * self.center;
+        self.rotation = (rotation * self.rotation).normalize();
+    }
+}
+
+impl BvhVolume for Obb3d {
+    const INFINITY: Self = Self {
+        center: Vec3A::ZERO,
+        rotation: Quat::IDENTITY,
+        half_size: Vec3A::INFINITY,
+    };
+
+    #[inline(always)]
+    fn morton_code(&self) -> u64 {
+        let center = self.center;
+        morton_encode(
+            morton_cell(center.x, MORTON_BITS_3D),
+            morton_cell(center.y, MORTON_BITS_3D),
+            morton_cell(center.z, MORTON_BITS_3D),
+        )
+    }
+}
+
+impl BvhItemVolume<Aabb3d> for Obb3d {
+    #[inline(always)]
+    fn node_volume(&self) -> Aabb3d {
+        self.aabb_3d()
+    }
+}
+
+impl IntersectsVolume<Obb3d> for Obb3d {
+    /// Test for overlap using the separating axis theorem, as described in Real-Time Collision
+    /// Detection (4.4.1)
+    fn intersects(&self, volume: &Obb3d) -> bool {
+        let (a, b) = (self, volume);
+        let a_axes = a.axes();
+        let rotation = (a_axes.transpose() * b.axes())
+            .transpose()
+            .to_cols_array_2d();
+        let offset = (a_axes.transpose() * (b.center - a.center)).to_array();
+        let (a_half, b_half) = (a.half_size.to_array(), b.half_size.to_array());
+
+        // Add an epsilon to counteract errors when two edges are parallel
+        let abs_rotation = rotation.map(|row| row.map(|v| v.abs() + 1e-6));
+
+        // Test the axes of a
+        for i in 0..3 {
+            let rb = (0..3).map(|j| b_half[j] * abs_rotation[i][j]).sum::<f32>();
+            if offset[i].abs() > a_half[i] + rb {
+                return false;
+            }
+        }
+
+        // Test the axes of b
+        for j in 0..3 {
+            let ra = (0..3).map(|i| a_half[i] * abs_rotation[i][j]).sum::<f32>();
+            let distance = (0..3).map(|i| offset[i] * rotation[i][j]).sum::<f32>();
+            if distance.abs() > ra + b_half[j] {
+                return false;
+            }
+        }
+
+        // Test the cross products of the axes
+        for i in 0..3 {
+            let (i1, i2) = ((i + 1) % 3, (i + 2) % 3);
+            for j in 0..3 {
+                let (j1, j2) = ((j + 1) % 3, (j + 2) % 3);
+                let ra = a_half[i1] * abs_rotation[i2][j] + a_half[i2] * abs_rotation[i1][j];
+                let rb = b_half[j1] * abs_rotation[i][j2] + b_half[j2] * abs_rotation[i][j1];
+                let distance = offset[i2] * rotation[i1][j] - offset[i1] * rotation[i2][j];
+                if distance.abs() > ra + rb {
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+}
+
+impl IntersectsVolume<Aabb3d> for Obb3d {
+    #[inline(always)]
+    fn intersects(&self, volume: &Aabb3d) -> bool {
+        self.intersects(&Obb3d::from(*volume))
+    }
+}
+
+impl IntersectsVolume<Obb3d> for Aabb3d {
+    #[inline(always)]
+    fn intersects(&self, volume: &Obb3d) -> bool {
+        volume.intersects(self)
+    }
+}
+
+impl IntersectsVolume<BoundingSphere> for Obb3d {
+    #[inline(always)]
+    fn intersects(&self, volume: &BoundingSphere) -> bool {
+        let local = self.to_local(volume.center());
+        let closest = local.clamp(-self.half_size, self.half_size);
+        let radius = volume.radius();
+        closest.distance_squared(local) <= radius * radius
+    }
+}
+
+impl IntersectsVolume<Obb3d> for BoundingSphere {
+    #[inline(always)]
+    fn intersects(&self, volume: &Obb3d) -> bool {
+        volume.intersects(self)
+    }
+}
+
+impl IntersectsVolume<Obb3d> for RayCast3d {
+    #[inline(always)]
+    fn intersects(&self, volume: &Obb3d) -> bool {
+        volume.ray_intersection_at(self).is_some()
+    }
+}
+
+/// Get the weight of the second volume when interpolating the rotation of a merged volume
+#[inline(always)]
+fn merge_weight<V: BoundingVolume>(a: &V, b: &V) -> f32 {
+    let (a, b) = (a.visible_area(), b.visible_area());
+    if a + b > 0. {
+        b / (a + b)
+    } else {
+        0.5
+    }
+}
+
+#[cfg(test)]
+use crate::dim3::BvhObb3d;
+
+#[test]
+fn test_obb() {
+    use core::f32::consts::FRAC_PI_4;
+
+    // A long beam rotated 45 degrees, which has a very loose AABB
+    let beam = Obb3d::new(
+        Vec3A::ZERO,
+        Quat::from_rotation_z(FRAC_PI_4),
+        Vec3A::new(10., 0.5, 0.5),
+    );
+    let bvh = BvhObb3d::new(1, [(0, beam)]);
+    let mut stack = bvh.create_stack();
+
+    // A box in the corner of the beam's AABB doesn't touch the beam itself
+    let corner = Aabb3d::new(Vec3A::new(5., -5., 0.), Vec3A::ONE);
+    assert!(beam.aabb_3d().intersects(&corner));
+    assert_eq!(bvh.traverse(&mut stack, corner).count(), 0);
+
+    let center = Aabb3d::new(Vec3A::new(5., 5., 0.), Vec3A::ONE);
+    assert_eq!(bvh.traverse(&mut stack, center).count(), 1);
+
+    let sphere = BoundingSphere::new(Vec3A::new(-5., -6.5, 0.), 0.5);
+    assert_eq!(bvh.traverse(&mut stack, sphere).count(), 0);
+    let sphere = BoundingSphere::new(Vec3A::new(-5., -6.5, 0.), 0.6);
+    assert_eq!(bvh.traverse(&mut stack, sphere).count(), 1);
+
+    let ray = RayCast3d::new(Vec3A::new(-5., 5., 0.), Dir3A::NEG_Y, 20.);
+    let distance = beam.ray_intersection_at(&ray).unwrap();
+    assert!((distance - (10. - core::f32::consts::SQRT_2 / 2.)).abs() < 1e-4);
+    let ray = RayCast3d::new(Vec3A::new(-7., 6., 0.), Dir3A::X, 4.);
+    assert!(ray.intersects(&beam.aabb_3d()));
+    assert_eq!(bvh.traverse(&mut stack, ray).count(), 0);
+
+    // Merging keeps both boxes contained
+    let other = Obb3d::new(
+        Vec3A::new(3., 0., 1.),
+        Quat::from_rotation_x(0.3),
+        Vec3A::ONE,
+    );
+    let merged = beam.merge(&other);
+    assert!(merged.grow(Vec3A::splat(1e-4)).contains(&beam));
+    assert!(merged.grow(Vec3A::splat(1e-4)).contains(&other));
+}
@@ -0,0 +1,195 @@
+//! A module with point queries, finding all items that contain a given point
+
+use crate::simd::prefetch;
+use crate::traverse::Stack;
+use crate::{Bvh, BvhItem, BvhItemVolume, BvhVolume};
+
+use bevy_math::{
+    bounding::{Aabb2d, Aabb3d, BoundingCircle, BoundingSphere, BoundingVolume, IntersectsVolume},
+    Vec2, Vec3A,
+};
+
+impl<Volume: BvhVolume, T, Item: BvhItemVolume<Volume>> Bvh<Volume, T, Item> {
+    /// Traverse the BVH, returning all items whose volume contains the point.
+    ///
+    /// Volumes are treated as closed, so points exactly on the boundary of a volume are included.
+    ///
+    /// This uses its own walk instead of [`Bvh::traverse`]: the point is compared with the volumes
+    /// directly through [`PointVolume`], children are only queued when they contain the point, and
+    /// the nodes or items below queued children get prefetched.
+    pub fn containing_point<'a>(
+        &'a self,
+        stack: &'a mut Stack,
+        point: impl Into<Volume::Translation>,
+    ) -> PointTraverser<'a, Volume, T, Item>
+    where
+        Volume: PointVolume,
+        Item: PointVolume<Translation = Volume::Translation>,
+    {
+        let point = point.into();
+        stack.clear();
+        if let Some(root) = self
+            .nodes
+            .first()
+            .filter(|root| root.volume.contains_point(&point))
+        {
+            prefetch(self, root);
+            stack.push_back(0);
+        }
+
+        PointTraverser {
+            bvh: self,
+            point,
+            stack,
+            items: [].iter(),
+        }
+    }
+}
+
+/// A volume that can test if it contains a point, used by [`Bvh::containing_point`].
+///
+/// A volume contains the point if the point lies inside it or on its boundary.
+pub trait PointVolume: BoundingVolume {
+    /// Check if the point is inside the volume or on its boundary
+    fn contains_point(&self, point: &Self::Translation) -> bool;
+}
+
+impl PointVolume for Aabb2d {
+    #[inline(always)]
+    fn contains_point(&self, point: &Vec2) -> bool {
+        point.cmpge(self.min).all() && point.cmple(self.max).all()
+    }
+}
+
+impl PointVolume for BoundingCircle {
+    #[inline(always)]
+    fn contains_point(&self, point: &Vec2) -> bool {
+        let radius = self.radius();
+        self.center().distance_squared(*point) <= radius * radius
+    }
+}
+
+impl PointVolume for Aabb3d {
+    #[inline(always)]
+    fn contains_point(&self, point: &Vec3A) -> bool {
+        point.cmpge(self.min).all() && point.cmple(self.max).all()
+    }
+}
+
+impl PointVolume for BoundingSphere {
+    #[inline(always)]
+    fn contains_point(&self, point: &Vec3A) -> bool {
+        let radius = self.radius();
+        self.center().distance_squared(*point) <= radius * radius
+    }
+}
+
+/// An iterator over the items containing a point, see [`Bvh::containing_point`]
+pub struct PointTraverser<'a, Volume: BvhVolume, T, Item: BvhItemVolume<Volume> = Volume> {
+    bvh: &'a Bvh<Volume, T, Item>,
+    /// The point the items contain
+    pub point: Volume::Translation,
+    stack: &'a mut Stack,
+    items: core::slice::Iter<'a, BvhItem<Item, T>>,
+}
+
+impl<'a, Volume, T, Item> Iterator for PointTraverser<'a, Volume, T, Item>
+where
+    Volume: BvhVolume + PointVolume,
+    Item: BvhItemVolume<Volume> + PointVolume<Translation = Volume::Translation>,
+{
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            for item in self.items.by_ref() {
+                if item.volume.contains_point(&self.point) {
+                    return Some(&item.t);
+                }
+            }
+
+            // Nodes in the queue already contain the point
+            let node = &self.bvh.nodes[self.stack.pop_front()? as usize];
+            let start = node.start_index;
+            if node.count > 0 {
+                let range = start as usize..(start + node.count) as usize;
+                self.items = self.bvh.items[range].iter();
+                continue;
+            }
+
+            for index in [start, start + 1] {
+                let child = &self.bvh.nodes[index as usize];
+                if child.volume.contains_point(&self.point) {
+                    prefetch(self.bvh, child);
+                    self.stack.push_back(index);
+                }
+            }
+        }
+    }
+}
+
+/// A point containment test for [`Bvh::traverse`], matching the volumes that contain the point
+/// like [`Bvh::containing_point`] does
+#[derive(Clone, Copy, Debug)]
+pub struct ContainsPoint<P>(pub P);
+
+impl<Volume: PointVolume> IntersectsVolume<Volume> for ContainsPoint<Volume::Translation> {
+    #[inline(always)]
+    fn intersects(&self, volume: &Volume) -> bool {
+        volume.contains_point(&self.0)
+    }
+}
+
+#[cfg(test)]
+use crate::{
+    dim2::BvhAabb2d,
+    dim3::{BvhAabb3d, BvhSphere},
+    random_boxes, random_point,
+};
+
+#[test]
+fn test_containing_point() {
+    let bvh = BvhAabb2d::new(
+        3,
+        [
+            (0, Aabb2d::new(Vec2::ZERO, Vec2::ONE)),
+            (1, Aabb2d::new(Vec2::splat(2.), Vec2::ONE)),
+            (2, Aabb2d::new(Vec2::splat(10.), Vec2::ONE)),
+        ],
+    );
+    let mut stack = bvh.create_stack();
+
+    // A point on the shared corner of two boxes is inside both
+    let mut hits = bvh
+        .containing_point(&mut stack, Vec2::ONE)
+        .copied()
+        .collect::<Vec<_>>();
+    hits.sort();
+    assert_eq!(hits, [0, 1]);
+
+    assert_eq!(bvh.containing_point(&mut stack, Vec2::splat(5.)).count(), 0);
+
+    let bvh = BvhSphere::new(1, [(0, BoundingSphere::new(Vec3A::ZERO, 1.))]);
+    let mut stack = bvh.create_stack();
+    assert_eq!(bvh.containing_point(&mut stack, Vec3A::X).count(), 1);
+    assert_eq!(bvh.containing_point(&mut stack, Vec3A::X * 1.01).count(), 0);
+
+    // The dedicated walk matches the items of a regular traversal
+    let boxes = random_boxes(1000, 100., 3.);
+    let bvh = BvhAabb3d::new(boxes.len(), boxes.into_iter().enumerate());
+    let mut stack = bvh.create_stack();
+    for _ in 0..100 {
+        let point = random_point(100.);
+        let mut expected = bvh
+            .traverse(&mut stack, ContainsPoint(point))
+            .copied()
+            .collect::<Vec<_>>();
+        expected.sort();
+        let mut hits = bvh
+            .containing_point(&mut stack, point)
+            .copied()
+            .collect::<Vec<_>>();
+        hits.sort();
+        assert_eq!(hits, expected);
+    }
+}
@@ -0,0 +1,342 @@
+//! A module with a compressed BVH for [`Aabb3d`] volumes, storing node bounds as 8 or 16-bit
+//! offsets relative to the parent node. Useful for large static trees where memory bandwidth
+//! dominates traversal.
+
+use crate::{ceil_log2, Bvh, BvhItem, BvhVolume};
+
+use alloc::{collections::VecDeque, vec::Vec};
+use core::fmt::Debug;
+
+use bevy_math::{
+    bounding::{Aabb3d, IntersectsVolume},
+    Vec3A,
+};
+
+/// An integer type that node bounds can be quantized to
+pub trait Quantization: Copy + Debug + Default {
+    /// The largest value, representing the maximum of the parent volume
+    const MAX: u32;
+
+    /// Convert from an integer that is at most [`Quantization::MAX`]
+    fn from_u32(v: u32) -> Self;
+
+    /// Convert to an integer
+    fn to_u32(self) -> u32;
+}
+
+impl Quantization for u8 {
+    const MAX: u32 = u8::MAX as u32;
+
+    #[inline(always)]
+    fn from_u32(v: u32) -> Self {
+        v as u8
+    }
+
+    #[inline(always)]
+    fn to_u32(self) -> u32 {
+        self as u32
+    }
+}
+
+impl Quantization for u16 {
+    const MAX: u32 = u16::MAX as u32;
+
+    #[inline(always)]
+    fn from_u32(v: u32) -> Self {
+        v as u16
+    }
+
+    #[inline(always)]
+    fn to_u32(self) -> u32 {
+        self as u32
+    }
+}
+
+/// A node on the [`QuantizedBvh`]
+#[derive(Clone, Copy, Debug)]
+pub struct QuantizedNode<Q: Quantization> {
+    /// The quantized minimum of the node, relative to the parent volume
+    pub min: [Q; 3],
+    /// The quantized maximum of the node, relative to the parent volume
+    pub max: [Q; 3],
+    /// The number of leaves. 0 if the node points to other nodes
+    pub count: u32,
+    /// The start index of the leaves. If count is 0 this points to other nodes
+    pub start_index: u32,
+}
+
+impl<Q: Quantization> QuantizedNode<Q> {
+    /// Get the volume of the node, given the decoded volume of its parent
+    #[inline(always)]
+    pub fn decode(&self, parent: &Aabb3d) -> Aabb3d {
+        Aabb3d {
+            min: dequantize::<Q>(parent, self.min.map(Q::to_u32)),
+            max: dequantize::<Q>(parent, self.max.map(Q::to_u32)),
+        }
+    }
+}
+
+/// A BVH over [`Aabb3d`] volumes with compressed node bounds, see [`QuantizedBvh::new`].
+///
+/// Node bounds are rounded outward, so queries return the same items as the original [`Bvh`].
+/// Items keep their full precision volumes.
+pub struct QuantizedBvh<T, Q: Quantization = u16> {
+    root: Aabb3d,
+    nodes: Vec<QuantizedNode<Q>>,
+    items: Vec<BvhItem<Aabb3d, T>>,
+}
+
+impl<T, Q: Quantization> QuantizedBvh<T, Q> {
+    /// Compress an existing BVH. Unused nodes are dropped in the process. The volumes in the BVH
+    /// must be finite.
+    pub fn new(bvh: Bvh<Aabb3d, T>) -> Self {
+        let Some(root) = bvh.nodes.first() else {
+            return Self {
+                root: Aabb3d::INFINITY,
+                nodes: Vec::new(),
+                items: bvh.items,
+            };
+        };
+        debug_assert!(root.volume.min.is_finite() && root.volume.max.is_finite());
+
+        let mut nodes = Vec::with_capacity(bvh.nodes.len());
+        nodes.push(QuantizedNode {
+            min: [Q::from_u32(0); 3],
+            max: [Q::from_u32(Q::MAX); 3],
+            count: root.count,
+            start_index: root.start_index,
+        });
+
+        // Children are placed next to each other, in the order they are reached
+        let mut queue = VecDeque::new();
+        if root.count == 0 {
+            queue.push_back((0, 0, root.volume));
+        }
+        while let Some((old, new, parent)) = queue.pop_front() {
+            let first = bvh.nodes[old].start_index as usize;
+            nodes[new].start_index = nodes.len() as u32;
+            for old in [first, first + 1] {
+                let node = &bvh.nodes[old];
+                let quantized = quantize::<Q>(&parent, &node.volume);
+                if node.count == 0 {
+                    queue.push_back((old, nodes.len(), quantized.decode(&parent)));
+                }
+                nodes.push(QuantizedNode {
+                    count: node.count,
+                    start_index: node.start_index,
+                    ..quantized
+                });
+            }
+        }
+
+        Self {
+            root: root.volume,
+            nodes,
+            items: bvh.items,
+        }
+    }
+
+    /// Get the full precision volume of the root node
+    pub fn root(&self) -> Aabb3d {
+        self.root
+    }
+
+    /// Get the number of nodes in the BVH
+    pub fn n_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Get the number of items in the BVH
+    pub fn n_items(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Get an iterator over the BVH's nodes
+    pub fn nodes(&self) -> impl Iterator<Item = &QuantizedNode<Q>> {
+        self.nodes.iter()
+    }
+
+    /// Get an iterator over the BVH's items
+    pub fn items(&self) -> impl Iterator<Item = &BvhItem<Aabb3d, T>> {
+        self.items.iter()
+    }
+
+    /// Create a stack with the right size for the BVH
+    pub fn create_stack(&self) -> QuantizedStack {
+        QuantizedStack(Vec::with_capacity(ceil_log2(self.items.len()) + 10))
+    }
+
+    /// Traverse the BVH with the provided [`IntersectsVolume`] test
+    pub fn traverse<'a, Test: IntersectsVolume<Aabb3d>>(
+        &'a self,
+        stack: &'a mut QuantizedStack,
+        tester: Test,
+    ) -> QuantizedTraverser<'a, T, Q, Test> {
+        stack.0.clear();
+        if !self.nodes.is_empty() {
+            stack.0.push((0, self.root));
+        }
+
+        QuantizedTraverser {
+            bvh: self,
+            tester,
+            stack,
+            items: 0..0,
+        }
+    }
+}
+
+impl<T, Q: Quantization> From<Bvh<Aabb3d, T>> for QuantizedBvh<T, Q> {
+    fn from(bvh: Bvh<Aabb3d, T>) -> Self {
+        Self::new(bvh)
+    }
+}
+
+/// A stack used when traversing the [`QuantizedBvh`], holding node indices along with their
+/// decoded volumes. You can reuse this to save on an alloc
+#[derive(Default)]
+pub struct QuantizedStack(Vec<(u32, Aabb3d)>);
+
+/// An iterator that traverses the [`QuantizedBvh`] using the provided [`IntersectsVolume`] test
+pub struct QuantizedTraverser<'a, T, Q: Quantization, Test: IntersectsVolume<Aabb3d>> {
+    bvh: &'a QuantizedBvh<T, Q>,
+    /// The test used in the traverser
+    pub tester: Test,
+    stack: &'a mut QuantizedStack,
+    items: core::ops::Range<usize>,
+}
+
+impl<'a, T, Q: Quantization, Test: IntersectsVolume<Aabb3d>> Iterator
+    for QuantizedTraverser<'a, T, Q, Test>
+{
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            for index in self.items.by_ref() {
+                let item = &self.bvh.items[index];
+                if self.tester.intersects(&item.volume) {
+                    return Some(&item.t);
+                }
+            }
+
+            let (index, volume) = self.stack.0.pop()?;
+            if !self.tester.intersects(&volume) {
+                continue;
+            }
+
+            let node = &self.bvh.nodes[index as usize];
+            if node.count > 0 {
+                let start = node.start_index as usize;
+                self.items = start..start + node.count as usize;
+            } else {
+                for child in [node.start_index + 1, node.start_index] {
+                    let decoded = self.bvh.nodes[child as usize].decode(&volume);
+                    self.stack.0.push((child, decoded));
+                }
+            }
+        }
+    }
+}
+
+/// Get the quantized bounds of the volume relative to the parent, rounded outward
+fn quantize<Q: Quantization>(parent: &Aabb3d, volume: &Aabb3d) -> QuantizedNode<Q> {
+    let extent = parent.max - parent.min;
+    let scale = Vec3A::select(
+        extent.cmpgt(Vec3A::ZERO),
+        Q::MAX as f32 / extent,
+        Vec3A::ZERO,
+    );
+    let mut min = ((volume.min - parent.min) * scale)
+        .floor()
+        .clamp(Vec3A::ZERO, Vec3A::splat(Q::MAX as f32))
+        .to_array()
+        .map(|v| v as u32);
+    let mut max = ((volume.max - parent.min) * scale)
+        .ceil()
+        .clamp(Vec3A::ZERO, Vec3A::splat(Q::MAX as f32))
+        .to_array()
+        .map(|v| v as u32);
+
+    // Float rounding can move the decoded bounds inward, step outward until they contain the volume
+    for axis in 0..3 {
+        while min[axis] > 0 && dequantize::<Q>(parent, min)[axis] > volume.min[axis] {
+            min[axis] -= 1;
+        }
+        while max[axis] < Q::MAX && dequantize::<Q>(parent, max)[axis] < volume.max[axis] {
+            max[axis] += 1;
+        }
+    }
+
+    QuantizedNode {
+        min: min.map(Q::from_u32),
+        max: max.map(Q::from_u32),
+        count: 0,
+        start_index: 0,
+    }
+}
+
+/// Get the position of the quantized value within the parent volume
+#[inline(always)]
+fn dequantize<Q: Quantization>(parent: &Aabb3d, value: [u32; 3]) -> Vec3A {
+    let t = Vec3A::from_array(value.map(|v| v as f32)) / Q::MAX as f32;
+    // The maximum maps to the parent exactly, so children touching it can always be contained
+    Vec3A::select(
+        t.cmpeq(Vec3A::ONE),
+        parent.max,
+        parent.min + (parent.max - parent.min) * t,
+    )
+}
+
+#[cfg(test)]
+use {
+    crate::{dim3::BvhAabb3d, random_boxes, random_point},
+    bevy_math::bounding::BoundingVolume,
+};
+
+#[test]
+fn test_quantized() {
+    let boxes = random_boxes(1000, 1000., 3.);
+    let bvh = BvhAabb3d::new(boxes.len(), boxes.iter().copied().enumerate());
+    let mut stack = bvh.create_stack();
+    let queries = (0..50)
+        .map(|_| Aabb3d::new(random_point(1000.), Vec3A::splat(30.)))
+        .collect::<Vec<_>>();
+    let expected = queries
+        .iter()
+        .map(|query| {
+            let mut hits = bvh
+                .traverse(&mut stack, *query)
+                .copied()
+                .collect::<Vec<_>>();
+            hits.sort();
+            hits
+        })
+        .collect::<Vec<_>>();
+
+    let quantized = QuantizedBvh::<_, u8>::new(bvh);
+    assert!(quantized.n_nodes() < 2 * boxes.len());
+    let mut stack = quantized.create_stack();
+    for (query, expected) in queries.iter().zip(expected) {
+        let mut hits = quantized
+            .traverse(&mut stack, *query)
+            .copied()
+            .collect::<Vec<_>>();
+        hits.sort();
+        assert_eq!(hits, expected);
+    }
+
+    // Every node must contain the items below it
+    let mut pending = vec![(0, quantized.root())];
+    while let Some((index, volume)) = pending.pop() {
+        let node = &quantized.nodes[index as usize];
+        if node.count > 0 {
+            let items = &quantized.items[node.start_index as usize..][..node.count as usize];
+            assert!(items.iter().all(|item| volume.contains(&item.volume)));
+        } else {
+            for child in [node.start_index, node.start_index + 1] {
+                pending.push((child, quantized.nodes[child as usize].decode(&volume)));
+            }
+        }
+    }
+}
@@ -0,0 +1,411 @@
+//! A module with specialized traversal for [`Aabb3d`] trees, using explicit SIMD lanes for the ray
+//! slab and overlap tests. Targets without SSE2 use a scalar fallback with the same results.
+
+use crate::traverse::Stack;
+use crate::{Bvh, BvhItem, BvhItemVolume, BvhNode, BvhVolume};
+
+use bevy_math::bounding::{Aabb3d, RayCast3d};
+
+impl<T> Bvh<Aabb3d, T> {
+    /// Traverse the BVH with a ray, returning the same items as [`Bvh::traverse`] with a
+    /// [`RayCast3d`]
+    pub fn traverse_ray<'a>(
+        &'a self,
+        stack: &'a mut Stack,
+        ray: RayCast3d,
+    ) -> SimdTraverser<'a, T, SimdRay3d> {
+        SimdTraverser::new(self, stack, SimdRay3d::from(ray))
+    }
+
+    /// Traverse the BVH with an [`Aabb3d`], returning the same items as [`Bvh::traverse`]
+    pub fn traverse_aabb<'a>(
+        &'a self,
+        stack: &'a mut Stack,
+        aabb: Aabb3d,
+    ) -> SimdTraverser<'a, T, SimdAabb3d> {
+        SimdTraverser::new(self, stack, SimdAabb3d::from(aabb))
+    }
+}
+
+/// An intersection test using SIMD lanes
+pub(crate) trait SimdTest {
+    /// Test the volume with the provided bounds
+    fn test(&self, min: F32x4, max: F32x4) -> bool;
+}
+
+/// A [`RayCast3d`] with its values stored in SIMD lanes, see [`Bvh::traverse_ray`]
+#[derive(Clone, Copy, Debug)]
+pub struct SimdRay3d {
+    origin: F32x4,
+    direction_recip: F32x4,
+    max: f32,
+}
+
+impl From<RayCast3d> for SimdRay3d {
+    fn from(ray: RayCast3d) -> Self {
+        Self {
+            origin: F32x4::from_vec3a(ray.origin),
+            direction_recip: F32x4::from_vec3a(ray.direction_recip()),
+            max: ray.max,
+        }
+    }
+}
+
+impl SimdTest for SimdRay3d {
+    #[inline(always)]
+    fn test(&self, min: F32x4, max: F32x4) -> bool {
+        let t1 = min.sub(self.origin).mul(self.direction_recip);
+        let t2 = max.sub(self.origin).mul(self.direction_recip);
+
+        let (near, far) = t1.min_max_ordered(t2);
+
+        let entry = near.max_element3().max(0.);
+        let exit = far.min_element3().min(self.max);
+        entry <= exit
+    }
+}
+
+/// An [`Aabb3d`] with its values stored in SIMD lanes, see [`Bvh::traverse_aabb`]
+#[derive(Clone, Copy, Debug)]
+pub struct SimdAabb3d {
+    min: F32x4,
+    max: F32x4,
+}
+
+impl From<Aabb3d> for SimdAabb3d {
+    fn from(aabb: Aabb3d) -> Self {
+        Self {
+            min: F32x4::from_vec3a(aabb.min),
+            max: F32x4::from_vec3a(aabb.max),
+        }
+    }
+}
+
+impl SimdTest for SimdAabb3d {
+    #[inline(always)]
+    fn test(&self, min: F32x4, max: F32x4) -> bool {
+        self.min.le3(max) && min.le3(self.max)
+    }
+}
+
+/// An iterator that traverses the BVH using SIMD lanes, see [`Bvh::traverse_ray`] and
+/// [`Bvh::traverse_aabb`].
+///
+/// Children are tested before they get queued, and the nodes or items below the queued children
+/// get prefetched, so they are in the cache by the time they are visited.
+pub struct SimdTraverser<'a, T, Test> {
+    bvh: &'a Bvh<Aabb3d, T>,
+    /// The test used in the traverser
+    pub tester: Test,
+    stack: &'a mut Stack,
+    items: core::slice::Iter<'a, BvhItem<Aabb3d, T>>,
+}
+
+impl<'a, T, Test> SimdTraverser<'a, T, Test> {
+    fn new(bvh: &'a Bvh<Aabb3d, T>, stack: &'a mut Stack, tester: Test) -> Self
+    where
+        Test: SimdTest,
+    {
+        stack.clear();
+        if let Some(root) = bvh.nodes.first().filter(|root| test(&tester, root)) {
+            prefetch(bvh, root);
+            stack.push_back(0);
+        }
+
+        Self {
+            bvh,
+            tester,
+            stack,
+            items: [].iter(),
+        }
+    }
+
+    #[inline(always)]
+    fn next_item(&mut self) -> Option<&'a T>
+    where
+        Test: SimdTest,
+    {
+        loop {
+            for item in self.items.by_ref() {
+                let min = F32x4::from_vec3a(item.volume.min);
+                let max = F32x4::from_vec3a(item.volume.max);
+                if self.tester.test(min, max) {
+                    return Some(&item.t);
+                }
+            }
+
+            // Nodes in the queue already passed the test
+            let node = &self.bvh.nodes[self.stack.pop_front()? as usize];
+            let start = node.start_index;
+            if node.count > 0 {
+                let range = start as usize..(start + node.count) as usize;
+                self.items = self.bvh.items[range].iter();
+                continue;
+            }
+
+            for index in [start, start + 1] {
+                let child = &self.bvh.nodes[index as usize];
+                if test(&self.tester, child) {
+                    prefetch(self.bvh, child);
+                    self.stack.push_back(index);
+                }
+            }
+        }
+    }
+}
+
+impl<'a, T> Iterator for SimdTraverser<'a, T, SimdRay3d> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_item()
+    }
+}
+
+impl<'a, T> Iterator for SimdTraverser<'a, T, SimdAabb3d> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_item()
+    }
+}
+
+#[inline(always)]
+fn bounds(node: &BvhNode<Aabb3d>) -> [F32x4; 2] {
+    [
+        F32x4::from_vec3a(node.volume.min),
+        F32x4::from_vec3a(node.volume.max),
+    ]
+}
+
+#[inline(always)]
+fn test(tester: &impl SimdTest, node: &BvhNode<Aabb3d>) -> bool {
+    let [min, max] = bounds(node);
+    tester.test(min, max)
+}
+
+/// Prefetch the children or items of a node, a no-op on targets without SSE2
+#[inline(always)]
+pub(crate) fn prefetch<Volume: BvhVolume, T, Item: BvhItemVolume<Volume>>(
+    bvh: &Bvh<Volume, T, Item>,
+    node: &BvhNode<Volume>,
+) {
+    let start = node.start_index as usize;
+    match node.count {
+        0 => lanes::prefetch(bvh.nodes.as_ptr().wrapping_add(start)),
+        _ => lanes::prefetch(bvh.items.as_ptr().wrapping_add(start)),
+    }
+}
+
+#[cfg(all(
+    any(target_arch = "x86", target_arch = "x86_64"),
+    target_feature = "sse2"
+))]
+mod lanes {
+    #[cfg(target_arch = "x86")]
+    use core::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use core::arch::x86_64::*;
+
+    use bevy_math::Vec3A;
+
+    /// Load the two cache lines at the pointer, which cover a pair of nodes
+    #[inline(always)]
+    pub(crate) fn prefetch<T>(ptr: *const T) {
+        let ptr = ptr as *const i8;
+        // SAFETY: Prefetching never faults, even for addresses outside of the allocation
+        unsafe {
+            _mm_prefetch::<_MM_HINT_T0>(ptr);
+            _mm_prefetch::<_MM_HINT_T0>(ptr.wrapping_add(64));
+        }
+    }
+
+    /// Four `f32` lanes, of which the first three are used
+    #[derive(Clone, Copy, Debug)]
+    pub(crate) struct F32x4(__m128);
+
+    // SAFETY: The intrinsics used below only require SSE2, which is enabled for this module
+    impl F32x4 {
+        #[inline(always)]
+        pub(crate) fn from_vec3a(v: Vec3A) -> Self {
+            Self(v.into())
+        }
+
+        #[inline(always)]
+        pub(crate) fn sub(self, other: Self) -> Self {
+            Self(unsafe { _mm_sub_ps(self.0, other.0) })
+        }
+
+        #[inline(always)]
+        pub(crate) fn mul(self, other: Self) -> Self {
+            Self(unsafe { _mm_mul_ps(self.0, other.0) })
+        }
+
+        #[inline(always)]
+        pub(crate) fn min_max_ordered(self, other: Self) -> (Self, Self) {
+            unsafe {
+                let ordered = _mm_cmpord_ps(self.0, other.0);
+                let select = |value: __m128, fallback: f32| {
+                    _mm_or_ps(
+                        _mm_and_ps(ordered, value),
+                        _mm_andnot_ps(ordered, _mm_set1_ps(fallback)),
+                    )
+                };
+                (
+                    Self(select(_mm_min_ps(self.0, other.0), -f32::INFINITY)),
+                    Self(select(_mm_max_ps(self.0, other.0), f32::INFINITY)),
+                )
+            }
+        }
+
+        #[inline(always)]
+        pub(crate) fn max_element3(self) -> f32 {
+            unsafe {
+                let y = _mm_shuffle_ps::<0b01_01_01_01>(self.0, self.0);
+                let z = _mm_shuffle_ps::<0b10_10_10_10>(self.0, self.0);
+                _mm_cvtss_f32(_mm_max_ps(_mm_max_ps(self.0, y), z))
+            }
+        }
+
+        #[inline(always)]
+        pub(crate) fn min_element3(self) -> f32 {
+            unsafe {
+                let y = _mm_shuffle_ps::<0b01_01_01_01>(self.0, self.0);
+                let z = _mm_shuffle_ps::<0b10_10_10_10>(self.0, self.0);
+                _mm_cvtss_f32(_mm_min_ps(_mm_min_ps(self.0, y), z))
+            }
+        }
+
+        #[inline(always)]
+        pub(crate) fn le3(self, other: Self) -> bool {
+            unsafe { _mm_movemask_ps(_mm_cmple_ps(self.0, other.0)) & 0b111 == 0b111 }
+        }
+    }
+}
+
+#[cfg(not(all(
+    any(target_arch = "x86", target_arch = "x86_64"),
+    target_feature = "sse2"
+)))]
+mod lanes {
+    use bevy_math::Vec3A;
+
+    #[inline(always)]
+    pub(crate) fn prefetch<T>(_ptr: *const T) {}
+
+    /// Four `f32` lanes, of which the first three are used
+    #[derive(Clone, Copy, Debug)]
+    pub(crate) struct F32x4([f32; 4]);
+
+    impl F32x4 {
+        #[inline(always)]
+        pub(crate) fn from_vec3a(v: Vec3A) -> Self {
+            Self([v.x, v.y, v.z, 0.])
+        }
+
+        #[inline(always)]
+        fn zip(self, other: Self, f: impl Fn(f32, f32) -> f32) -> Self {
+            Self(core::array::from_fn(|i| f(self.0[i], other.0[i])))
+        }
+
+        #[inline(always)]
+        pub(crate) fn sub(self, other: Self) -> Self {
+            self.zip(other, |a, b| a - b)
+        }
+
+        #[inline(always)]
+        pub(crate) fn mul(self, other: Self) -> Self {
+            self.zip(other, |a, b| a * b)
+        }
+
+        #[inline(always)]
+        pub(crate) fn min_max_ordered(self, other: Self) -> (Self, Self) {
+            let ordered = |i: usize| !self.0[i].is_nan() && !other.0[i].is_nan();
+            (
+                Self(core::array::from_fn(|i| match ordered(i) {
+                    true => self.0[i].min(other.0[i]),
+                    false => -f32::INFINITY,
+                })),
+                Self(core::array::from_fn(|i| match ordered(i) {
+                    true => self.0[i].max(other.0[i]),
+                    false => f32::INFINITY,
+                })),
+            )
+        }
+
+        #[inline(always)]
+        pub(crate) fn max_element3(self) -> f32 {
+            self.0[0].max(self.0[1]).max(self.0[2])
+        }
+
+        #[inline(always)]
+        pub(crate) fn min_element3(self) -> f32 {
+            self.0[0].min(self.0[1]).min(self.0[2])
+        }
+
+        #[inline(always)]
+        pub(crate) fn le3(self, other: Self) -> bool {
+            (0..3).all(|i| self.0[i] <= other.0[i])
+        }
+    }
+}
+
+use lanes::F32x4;
+
+#[cfg(test)]
+use {
+    crate::{dim3::BvhAabb3d, random_boxes, random_point},
+    bevy_math::{Dir3A, Vec3A},
+};
+
+#[test]
+fn test_simd() {
+    let boxes = random_boxes(1000, 20., 2.);
+    let bvh = BvhAabb3d::new(boxes.len(), boxes.into_iter().enumerate());
+    let mut stack = bvh.create_stack();
+
+    let sorted = |hits: &mut dyn Iterator<Item = &usize>| {
+        let mut hits = hits.copied().collect::<Vec<_>>();
+        hits.sort();
+        hits
+    };
+    for _ in 0..100 {
+        let origin = random_point(24.) - 2.;
+        let direction = Dir3A::new(random_point(1.) - 0.5).unwrap();
+        let ray = RayCast3d::new(origin, direction, 15.);
+        let expected = sorted(&mut bvh.traverse(&mut stack, ray.clone()));
+        assert_eq!(sorted(&mut bvh.traverse_ray(&mut stack, ray)), expected);
+
+        let aabb = Aabb3d::new(origin, random_point(4.));
+        let expected = sorted(&mut bvh.traverse(&mut stack, aabb));
+        assert_eq!(sorted(&mut bvh.traverse_aabb(&mut stack, aabb)), expected);
+    }
+
+    // Rays starting exactly on the faces of a grid of cubes, without moving along that axis,
+    // produce NaN in the slab test. Like bevy's test they touch the cubes on both sides
+    let cubes = (0..1000).map(|i| {
+        let min = Vec3A::new((i % 10) as f32, (i / 10 % 10) as f32, (i / 100) as f32);
+        (i, Aabb3d::new(min + 0.5, Vec3A::splat(0.5)))
+    });
+    let bvh = BvhAabb3d::new(1000, cubes);
+    for face in 0..=11 {
+        let offset = face as f32;
+        let rays = [
+            (Vec3A::new(-1., offset, 0.5), Dir3A::X),
+            (Vec3A::new(11., offset, 0.5), Dir3A::NEG_X),
+            (Vec3A::new(offset, -1., 0.5), Dir3A::Y),
+            (Vec3A::new(offset, 11., 0.5), Dir3A::NEG_Y),
+        ];
+        for (origin, direction) in rays {
+            let ray = RayCast3d::new(origin, direction, 15.);
+            let expected = sorted(&mut bvh.traverse(&mut stack, ray.clone()));
+            let rows = match face {
+                0 | 10 => 1,
+                11 => 0,
+                _ => 2,
+            };
+            assert_eq!(expected.len(), 10 * rows);
+            assert_eq!(sorted(&mut bvh.traverse_ray(&mut stack, ray)), expected);
+        }
+    }
+}
@@ -0,0 +1,159 @@
+//! A module for traversing the BVH without a stack, following skip links to the next subtree
+//! whenever a node is missed. Queries only keep a node index around, so they don't allocate.
+
+use crate::{Bvh, BvhItem, BvhItemVolume, BvhVolume};
+
+use bevy_math::bounding::IntersectsVolume;
+
+/// The skip link of nodes without a next subtree, ending the traversal
+const END: u32 = u32::MAX;
+
+impl<Volume: BvhVolume, T, Item: BvhItemVolume<Volume>> Bvh<Volume, T, Item> {
+    /// Store a skip link for each node, pointing to the next subtree to visit once the node is
+    /// done. Without them [`Bvh::traverse_stackless`] walks the parent links instead, which costs
+    /// a few steps per missed subtree
+    pub fn compute_skip_links(&mut self) {
+        self.skip_links = (0..self.nodes.len() as u32)
+            .map(|index| self.find_skip_link(index))
+            .collect();
+    }
+
+    /// Check if [`Bvh::compute_skip_links`] has been called
+    pub fn has_skip_links(&self) -> bool {
+        self.skip_links.len() == self.nodes.len() && !self.nodes.is_empty()
+    }
+
+    /// Traverse the BVH with the provided [`IntersectsVolume`] test, without allocating. The test
+    /// is used on both the node and the item volumes
+    pub fn traverse_stackless<Test: IntersectsVolume<Volume> + IntersectsVolume<Item>>(
+        &self,
+        tester: Test,
+    ) -> StacklessTraverser<'_, Volume, T, Test, Item> {
+        StacklessTraverser {
+            bvh: self,
+            tester,
+            node: if self.nodes.is_empty() { END } else { 0 },
+            items: [].iter(),
+        }
+    }
+
+    #[inline(always)]
+    fn skip_link(&self, index: u32) -> u32 {
+        match self.skip_links.get(index as usize) {
+            Some(link) => *link,
+            None => self.find_skip_link(index),
+        }
+    }
+
+    /// Find the next subtree by walking up until the node is a first child, then taking its sibling
+    fn find_skip_link(&self, index: u32) -> u32 {
+        let mut index = index;
+        loop {
+            let parent = self.parents[index as usize];
+            if parent == END {
+                return END;
+            }
+            if self.nodes[parent as usize].start_index == index {
+                return index + 1;
+            }
+            index = parent;
+        }
+    }
+}
+
+/// An iterator that traverses the BVH using the provided [`IntersectsVolume`] test, see
+/// [`Bvh::traverse_stackless`]
+pub struct StacklessTraverser<
+    'a,
+    Volume: BvhVolume,
+    T,
+    Test: IntersectsVolume<Volume> + IntersectsVolume<Item>,
+    Item: BvhItemVolume<Volume> = Volume,
+> {
+    bvh: &'a Bvh<Volume, T, Item>,
+    /// The test used in the traverser
+    pub tester: Test,
+    node: u32,
+    items: core::slice::Iter<'a, BvhItem<Item, T>>,
+}
+
+impl<
+        'a,
+        Volume: BvhVolume,
+        T,
+        Test: IntersectsVolume<Volume> + IntersectsVolume<Item>,
+        Item: BvhItemVolume<Volume>,
+    > Iterator for StacklessTraverser<'a, Volume, T, Test, Item>
+{
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            for item in self.items.by_ref() {
+                if IntersectsVolume::<Item>::intersects(&self.tester, &item.volume) {
+                    return Some(&item.t);
+                }
+            }
+
+            if self.node == END {
+                return None;
+            }
+
+            let node = &self.bvh.nodes[self.node as usize];
+            if !IntersectsVolume::<Volume>::intersects(&self.tester, &node.volume) {
+                self.node = self.bvh.skip_link(self.node);
+                continue;
+            }
+
+            if node.count == 0 {
+                self.node = node.start_index;
+                continue;
+            }
+
+            let start = node.start_index as usize;
+            self.items = self.bvh.items[start..start + node.count as usize].iter();
+            self.node = self.bvh.skip_link(self.node);
+        }
+    }
+}
+
+#[cfg(test)]
+use crate::{
+    dim3::{Aabb3d, BvhAabb3d, Vec3A},
+    random_boxes, random_point,
+};
+
+#[test]
+fn test_stackless() {
+    let boxes = random_boxes(1000, 100., 1.);
+    let mut bvh = BvhAabb3d::new(1000, boxes.into_iter().enumerate());
+    let mut stack = bvh.create_stack();
+    let queries = (0..50)
+        .map(|_| Aabb3d::new(random_point(100.), Vec3A::splat(8.)))
+        .collect::<Vec<_>>();
+    let sorted = |hits: &mut dyn Iterator<Item = &usize>| {
+        let mut hits = hits.copied().collect::<Vec<_>>();
+        hits.sort();
+        hits
+    };
+    let expected = queries
+        .iter()
+        .map(|query| sorted(&mut bvh.traverse(&mut stack, *query)))
+        .collect::<Vec<_>>();
+
+    assert!(!bvh.has_skip_links());
+    for (query, expected) in queries.iter().zip(&expected) {
+        assert_eq!(&sorted(&mut bvh.traverse_stackless(*query)), expected);
+    }
+
+    bvh.compute_skip_links();
+    assert!(bvh.has_skip_links());
+    for (query, expected) in queries.iter().zip(&expected) {
+        assert_eq!(&sorted(&mut bvh.traverse_stackless(*query)), expected);
+    }
+
+    // The infinite volume reaches every item
+    assert_eq!(bvh.traverse_stackless(Aabb3d::INFINITY).count(), 1000);
+    let empty = BvhAabb3d::<usize>::default();
+    assert_eq!(empty.traverse_stackless(Aabb3d::INFINITY).count(), 0);
+}
@@ -0,0 +1,305 @@
+//! A module with swept volume queries, finding the candidates a moving volume could hit.
+//! This is useful for continuous collision detection, where fast moving bodies would otherwise
+//! tunnel through thin geometry.
+
+use crate::traverse::{Stack, Traverser};
+use crate::{Bvh, BvhVolume};
+
+use bevy_math::{
+    bounding::{Aabb3d, BoundingSphere, BoundingVolume, IntersectsVolume},
+    ops, Vec3A,
+};
+
+/// A swept volume test that can compute when the moving volume first touches another volume
+pub trait SweepTest<Volume: BvhVolume>: IntersectsVolume<Volume> {
+    /// Get the time of first contact with the volume, between 0 (start pose) and 1 (end pose).
+    ///
+    /// The time is conservative, the moving volume never touches the volume before this time.
+    fn entry_time(&self, volume: &Volume) -> Option<f32>;
+}
+
+impl<Volume: BvhVolume, T> Bvh<Volume, T> {
+    /// Traverse the BVH with a swept volume, returning all items the volume could hit along with
+    /// a conservative entry time between 0 and 1. Items are not sorted by their entry time.
+    pub fn sweep<'a, Test: SweepTest<Volume>>(
+        &'a self,
+        stack: &'a mut Stack,
+        sweep: Test,
+    ) -> Sweeper<'a, Volume, T, Test> {
+        Sweeper(self.traverse(stack, sweep))
+    }
+}
+
+/// An iterator over the candidates of a swept volume query, see [`Bvh::sweep`]
+pub struct Sweeper<'a, Volume: BvhVolume, T, Test: SweepTest<Volume>>(
+    Traverser<'a, Volume, T, Test>,
+);
+
+impl<'a, Volume: BvhVolume, T, Test: SweepTest<Volume>> Iterator for Sweeper<'a, Volume, T, Test> {
+    type Item = (&'a T, f32);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(item) = self.0.next_entry() {
+            if let Some(time) = self.0.tester.entry_time(&item.volume) {
+                return Some((&item.t, time));
+            }
+        }
+        None
+    }
+}
+
+/// An [`Aabb3d`] moving along a straight line
+#[derive(Clone, Copy, Debug)]
+pub struct SweptAabb3d {
+    /// The volume at the start of the motion
+    pub aabb: Aabb3d,
+    /// The translation from the start to the end of the motion
+    pub motion: Vec3A,
+}
+
+impl SweptAabb3d {
+    /// Create a swept volume moving from the start to the end pose. If the poses differ in size,
+    /// the larger half size is used for the entire motion.
+    pub fn new(start: Aabb3d, end: Aabb3d) -> Self {
+        let half_size = start.half_size().max(end.half_size());
+        Self {
+            aabb: Aabb3d::new(start.center(), half_size),
+            motion: end.center() - start.center(),
+        }
+    }
+
+    /// Create a swept volume moving with the provided velocity, for a single unit of time
+    pub fn from_velocity(aabb: Aabb3d, velocity: impl Into<Vec3A>) -> Self {
+        Self {
+            aabb,
+            motion: velocity.into(),
+        }
+    }
+
+    /// Get the hull of the entire motion
+    pub fn hull(&self) -> Aabb3d {
+        self.aabb.merge(&self.aabb.translated_by(self.motion))
+    }
+}
+
+impl SweepTest<Aabb3d> for SweptAabb3d {
+    #[inline(always)]
+    fn entry_time(&self, volume: &Aabb3d) -> Option<f32> {
+        let half_size = self.aabb.half_size();
+        slab_entry(
+            self.aabb.center(),
+            self.motion,
+            volume.min - half_size,
+            volume.max + half_size,
+        )
+    }
+}
+
+impl SweepTest<BoundingSphere> for SweptAabb3d {
+    #[inline(always)]
+    fn entry_time(&self, volume: &BoundingSphere) -> Option<f32> {
+        self.entry_time(&volume.aabb_3d())
+    }
+}
+
+impl IntersectsVolume<Aabb3d> for SweptAabb3d {
+    #[inline(always)]
+    fn intersects(&self, volume: &Aabb3d) -> bool {
+        self.entry_time(volume).is_some()
+    }
+}
+
+impl IntersectsVolume<BoundingSphere> for SweptAabb3d {
+    #[inline(always)]
+    fn intersects(&self, volume: &BoundingSphere) -> bool {
+        self.entry_time(volume).is_some()
+    }
+}
+
+/// A [`BoundingSphere`] moving along a straight line
+#[derive(Clone, Copy, Debug)]
+pub struct SweptSphere {
+    /// The volume at the start of the motion
+    pub sphere: BoundingSphere,
+    /// The translation from the start to the end of the motion
+    pub motion: Vec3A,
+}
+
+impl SweptSphere {
+    /// Create a swept volume moving from the start to the end pose. If the poses differ in size,
+    /// the larger radius is used for the entire motion.
+    pub fn new(start: BoundingSphere, end: BoundingSphere) -> Self {
+        Self {
+            sphere: BoundingSphere::new(start.center(), start.radius().max(end.radius())),
+            motion: end.center() - start.center(),
+        }
+    }
+
+    /// Create a swept volume moving with the provided velocity, for a single unit of time
+    pub fn from_velocity(sphere: BoundingSphere, velocity: impl Into<Vec3A>) -> Self {
+        Self {
+            sphere,
+            motion: velocity.into(),
+        }
+    }
+
+    /// Get the hull of the entire motion
+    pub fn hull(&self) -> Aabb3d {
+        let aabb = self.sphere.aabb_3d();
+        aabb.merge(&aabb.translated_by(self.motion))
+    }
+}
+
+impl SweepTest<Aabb3d> for SweptSphere {
+    #[inline(always)]
+    fn entry_time(&self, volume: &Aabb3d) -> Option<f32> {
+        let radius = self.sphere.radius();
+        slab_entry(
+            self.sphere.center(),
+            self.motion,
+            volume.min - radius,
+            volume.max + radius,
+        )
+    }
+}
+
+impl SweepTest<BoundingSphere> for SweptSphere {
+    #[inline(always)]
+    fn entry_time(&self, volume: &BoundingSphere) -> Option<f32> {
+        sphere_entry(
+            self.sphere.center(),
+            self.motion,
+            volume.center(),
+            self.sphere.radius() + volume.radius(),
+        )
+    }
+}
+
+impl IntersectsVolume<Aabb3d> for SweptSphere {
+    #[inline(always)]
+    fn intersects(&self, volume: &Aabb3d) -> bool {
+        self.entry_time(volume).is_some()
+    }
+}
+
+impl IntersectsVolume<BoundingSphere> for SweptSphere {
+    #[inline(always)]
+    fn intersects(&self, volume: &BoundingSphere) -> bool {
+        self.entry_time(volume).is_some()
+    }
+}
+
+/// Get the time a point moving along the motion enters the box, if it does so before the motion ends
+#[inline(always)]
+fn slab_entry(origin: Vec3A, motion: Vec3A, min: Vec3A, max: Vec3A) -> Option<f32> {
+    // Axes without motion don't limit the range if the point is inside the slab, and reject it
+    // otherwise. Dividing by zero would produce NaN on the boundary, so they are masked out
+    let moving = motion.cmpne(Vec3A::ZERO);
+    let inside = origin.cmpge(min) & origin.cmple(max);
+    if !(moving | inside).all() {
+        return None;
+    }
+
+    let recip = motion.recip();
+    let t1 = (min - origin) * recip;
+    let t2 = (max - origin) * recip;
+    let near = Vec3A::select(moving, t1.min(t2), Vec3A::NEG_INFINITY);
+    let far = Vec3A::select(moving, t1.max(t2), Vec3A::INFINITY);
+
+    let entry = near.max_element().max(0.);
+    let exit = far.min_element().min(1.);
+
+    (entry <= exit).then_some(entry)
+}
+
+/// Get the time a point moving along the motion enters the sphere, if it does so before the motion ends
+#[inline(always)]
+fn sphere_entry(origin: Vec3A, motion: Vec3A, center: Vec3A, radius: f32) -> Option<f32> {
+    let offset = origin - center;
+    let c = offset.length_squared() - radius * radius;
+    if c <= 0. {
+        return Some(0.);
+    }
+
+    let b = offset.dot(motion);
+    let a = motion.length_squared();
+    if b >= 0. || a == 0. {
+        // We are outside the sphere and not moving towards it
+        return None;
+    }
+
+    let discriminant = b * b - a * c;
+    if discriminant < 0. {
+        return None;
+    }
+
+    let time = (-b - ops::sqrt(discriminant)) / a;
+    (time <= 1.).then_some(time)
+}
+
+#[cfg(test)]
+use crate::dim3::BvhAabb3d;
+
+#[test]
+fn test_sweep() {
+    // A thin wall that a fast moving box would skip over between the two poses
+    let bvh = BvhAabb3d::new(
+        2,
+        [
+            (
+                0,
+                Aabb3d::new(Vec3A::new(5., 0., 0.), Vec3A::new(0.05, 2., 2.)),
+            ),
+            (1, Aabb3d::new(Vec3A::new(5., 10., 0.), Vec3A::ONE)),
+        ],
+    );
+    let mut stack = bvh.create_stack();
+
+    let start = Aabb3d::new(Vec3A::ZERO, Vec3A::splat(0.5));
+    let end = Aabb3d::new(Vec3A::new(10., 0., 0.), Vec3A::splat(0.5));
+    let hits = bvh
+        .sweep(&mut stack, SweptAabb3d::new(start, end))
+        .map(|(t, time)| (*t, time))
+        .collect::<Vec<_>>();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].0, 0);
+    assert!((hits[0].1 - 0.445).abs() < 1e-4);
+
+    let sphere = BoundingSphere::new(Vec3A::ZERO, 0.5);
+    let hits = bvh
+        .sweep(
+            &mut stack,
+            SweptSphere::from_velocity(sphere, Vec3A::Y * 20.),
+        )
+        .count();
+    assert_eq!(hits, 0);
+    let hits = bvh
+        .sweep(
+            &mut stack,
+            SweptSphere::from_velocity(sphere, Vec3A::X * 20.),
+        )
+        .count();
+    assert_eq!(hits, 1);
+
+    // Touching a volume on an axis without motion is a hit, like the static overlap
+    let wall = Aabb3d {
+        min: Vec3A::new(5., 0.5, -1.),
+        max: Vec3A::new(5.1, 2., 1.),
+    };
+    let bvh = BvhAabb3d::new(1, [(0, wall)]);
+    let sweep = SweptAabb3d::from_velocity(start, Vec3A::new(10., 0., 0.));
+    assert_eq!(bvh.traverse(&mut stack, sweep.hull()).count(), 1);
+    assert_eq!(bvh.sweep(&mut stack, sweep).count(), 1);
+
+    let touching = Aabb3d {
+        min: Vec3A::new(0.5, -1., -1.),
+        max: Vec3A::new(1., 1., 1.),
+    };
+    let bvh = BvhAabb3d::new(1, [(0, touching)]);
+    assert_eq!(bvh.traverse(&mut stack, start).count(), 1);
+    let hits = bvh
+        .sweep(&mut stack, SweptAabb3d::from_velocity(start, Vec3A::ZERO))
+        .map(|(_, time)| time)
+        .collect::<Vec<_>>();
+    assert_eq!(hits, [0.]);
+}
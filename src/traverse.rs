@@ -1,8 +1,8 @@
 //! A module with generic logic for traversing the BVH
 
-use crate::{Bvh, BvhNode, BvhVolume};
+use crate::{ceil_log2, Bvh, BvhItem, BvhItemVolume, BvhNode, BvhVolume};
 
-use std::collections::VecDeque;
+use alloc::collections::VecDeque;
 
 use bevy_math::bounding::IntersectsVolume;
 
@@ -10,7 +10,7 @@ use bevy_math::bounding::IntersectsVolume;
 #[derive(Default)]
 pub struct Stack(VecDeque<u32>);
 
-impl std::ops::Deref for Stack {
+impl core::ops::Deref for Stack {
     type Target = VecDeque<u32>;
 
     fn deref(&self) -> &Self::Target {
@@ -18,29 +18,28 @@ impl std::ops::Deref for Stack {
     }
 }
 
-impl std::ops::DerefMut for Stack {
+impl core::ops::DerefMut for Stack {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.0
     }
 }
 
-impl<Volume: BvhVolume, T: Copy> Bvh<Volume, T> {
+impl<Volume: BvhVolume, T, Item: BvhItemVolume<Volume>> Bvh<Volume, T, Item> {
     /// Create a stack with the right size for the BVH
     pub fn create_stack(&self) -> Stack {
         // TODO: Make sure we use the correct value here
-        Stack(VecDeque::with_capacity(
-            (self.items.len() as f32).log2().ceil() as usize + 10,
-        ))
+        Stack(VecDeque::with_capacity(ceil_log2(self.items.len()) + 10))
     }
 
-    /// Traverse the BVH with the provided [`IntersectsVolume`] test
-    pub fn traverse<'a, Test: IntersectsVolume<Volume>>(
+    /// Traverse the BVH with the provided [`IntersectsVolume`] test. The test is used on both the
+    /// node and the item volumes
+    pub fn traverse<'a, Test: IntersectsVolume<Volume> + IntersectsVolume<Item>>(
         &'a self,
         stack: &'a mut Stack,
         tester: Test,
-    ) -> Traverser<'a, Volume, T, Test> {
+    ) -> Traverser<'a, Volume, T, Test, Item> {
         stack.clear();
-        stack.reserve_exact((self.items.len() as f32).log2().ceil() as usize + 10);
+        stack.reserve_exact(ceil_log2(self.items.len()) + 10);
         stack.push_back(0);
 
         Traverser {
@@ -54,8 +53,14 @@ impl<Volume: BvhVolume, T: Copy> Bvh<Volume, T> {
 }
 
 /// An iterator that traverse the BVH using the provided [`IntersectsVolume`] test
-pub struct Traverser<'a, Volume: BvhVolume, T: Copy, Test: IntersectsVolume<Volume>> {
-    bvh: &'a Bvh<Volume, T>,
+pub struct Traverser<
+    'a,
+    Volume: BvhVolume,
+    T,
+    Test: IntersectsVolume<Volume> + IntersectsVolume<Item>,
+    Item: BvhItemVolume<Volume> = Volume,
+> {
+    bvh: &'a Bvh<Volume, T, Item>,
     /// The test used in the traverser
     pub tester: Test,
     stack: &'a mut Stack,
@@ -63,12 +68,31 @@ pub struct Traverser<'a, Volume: BvhVolume, T: Copy, Test: IntersectsVolume<Volu
     offset: u32,
 }
 
-impl<'a, Volume: BvhVolume, T: Copy, Test: IntersectsVolume<Volume>> Iterator
-    for Traverser<'a, Volume, T, Test>
+impl<
+        'a,
+        Volume: BvhVolume,
+        T,
+        Test: IntersectsVolume<Volume> + IntersectsVolume<Item>,
+        Item: BvhItemVolume<Volume>,
+    > Iterator for Traverser<'a, Volume, T, Test, Item>
 {
     type Item = &'a T;
 
     fn next(&mut self) -> Option<Self::Item> {
+        self.next_entry().map(|item| &item.t)
+    }
+}
+
+impl<
+        'a,
+        Volume: BvhVolume,
+        T,
+        Test: IntersectsVolume<Volume> + IntersectsVolume<Item>,
+        Item: BvhItemVolume<Volume>,
+    > Traverser<'a, Volume, T, Test, Item>
+{
+    /// Get the next item that passes the test, including its volume
+    pub(crate) fn next_entry(&mut self) -> Option<&'a BvhItem<Item, T>> {
         if self.bvh.items.is_empty() {
             return None;
         }
@@ -84,7 +108,7 @@ impl<'a, Volume: BvhVolume, T: Copy, Test: IntersectsVolume<Volume>> Iterator
         while let Some(index) = self.stack.pop_front() {
             let node = &self.bvh.nodes[index as usize];
 
-            if !self.tester.intersects(&node.volume) {
+            if !IntersectsVolume::<Volume>::intersects(&self.tester, &node.volume) {
                 continue;
             }
 
@@ -104,21 +128,17 @@ impl<'a, Volume: BvhVolume, T: Copy, Test: IntersectsVolume<Volume>> Iterator
 
         None
     }
-}
 
-impl<'a, Volume: BvhVolume, T: Copy, Test: IntersectsVolume<Volume>>
-    Traverser<'a, Volume, T, Test>
-{
     #[inline(always)]
-    fn next_item(&mut self, node: &'_ BvhNode<Volume>) -> Option<&'a T> {
+    fn next_item(&mut self, node: &'_ BvhNode<Volume>) -> Option<&'a BvhItem<Item, T>> {
         while self.current_node.is_some() {
             let item = &self.bvh.items[(node.start_index + self.offset) as usize];
             self.offset += 1;
             if self.offset == node.count {
                 self.current_node = None;
             }
-            if self.tester.intersects(&item.volume) {
-                return Some(&item.t);
+            if IntersectsVolume::<Item>::intersects(&self.tester, &item.volume) {
+                return Some(item);
             }
         }
         None
@@ -0,0 +1,450 @@
+//! A module with wide BVHs for [`Aabb3d`] volumes, where each node holds the bounds of up to `W`
+//! children in structure-of-arrays form. This allows testing all children with a single node fetch,
+//! and lets the [`Aabb3d`] and [`RayCast3d`] tests run lane by lane over the children, see
+//! [`WideBvh::traverse_lanes`].
+
+use crate::traverse::Stack;
+use crate::{ceil_log2, Bvh, BvhItem, BvhItemVolume};
+
+use alloc::{collections::VecDeque, vec::Vec};
+
+use bevy_math::{
+    bounding::{Aabb3d, BoundingVolume, IntersectsVolume, RayCast3d},
+    Vec3A,
+};
+
+/// A wide BVH with 4 children per node
+pub type Bvh4<T, Item = Aabb3d> = WideBvh<4, T, Item>;
+
+/// A wide BVH with 8 children per node
+pub type Bvh8<T, Item = Aabb3d> = WideBvh<8, T, Item>;
+
+/// A node on the [`WideBvh`], storing the bounds of its children as structure-of-arrays
+#[derive(Clone, Copy, Debug)]
+pub struct WideNode<const W: usize> {
+    /// The minimum x of each child
+    pub min_x: [f32; W],
+    /// The minimum y of each child
+    pub min_y: [f32; W],
+    /// The minimum z of each child
+    pub min_z: [f32; W],
+    /// The maximum x of each child
+    pub max_x: [f32; W],
+    /// The maximum y of each child
+    pub max_y: [f32; W],
+    /// The maximum z of each child
+    pub max_z: [f32; W],
+    /// The number of leaves of each child. 0 if the child is another node
+    pub count: [u32; W],
+    /// The start index of the leaves of each child. If count is 0 this points to another node
+    pub start_index: [u32; W],
+    /// The number of children in use
+    pub len: u32,
+}
+
+impl<const W: usize> WideNode<W> {
+    const EMPTY: Self = Self {
+        min_x: [f32::INFINITY; W],
+        min_y: [f32::INFINITY; W],
+        min_z: [f32::INFINITY; W],
+        max_x: [-f32::INFINITY; W],
+        max_y: [-f32::INFINITY; W],
+        max_z: [-f32::INFINITY; W],
+        count: [0; W],
+        start_index: [0; W],
+        len: 0,
+    };
+
+    /// Get the volume of a child
+    #[inline(always)]
+    pub fn child_volume(&self, child: usize) -> Aabb3d {
+        Aabb3d {
+            min: Vec3A::new(self.min_x[child], self.min_y[child], self.min_z[child]),
+            max: Vec3A::new(self.max_x[child], self.max_y[child], self.max_z[child]),
+        }
+    }
+
+    /// Test the children with the [`WideTest`], returning a mask with a bit set for each child that
+    /// passes
+    #[inline(always)]
+    pub fn intersects(&self, tester: &impl WideTest) -> u32 {
+        tester.intersects_children(self)
+    }
+
+    /// Get a mask with a bit set for each child in use
+    #[inline(always)]
+    fn used(&self) -> u32 {
+        u32::MAX >> (32 - self.len)
+    }
+
+    #[inline(always)]
+    fn set_child(&mut self, child: usize, volume: &Aabb3d, count: u32, start_index: u32) {
+        self.min_x[child] = volume.min.x;
+        self.min_y[child] = volume.min.y;
+        self.min_z[child] = volume.min.z;
+        self.max_x[child] = volume.max.x;
+        self.max_y[child] = volume.max.y;
+        self.max_z[child] = volume.max.z;
+        self.count[child] = count;
+        self.start_index[child] = start_index;
+    }
+}
+
+/// A test used on the children of a [`WideNode`]. The [`Aabb3d`] and [`RayCast3d`] tests run on
+/// all lanes of the node at once, other tests can use [`PerChild`]
+pub trait WideTest {
+    /// Test the children of the node, returning a mask with a bit set for each child that passes
+    fn intersects_children<const W: usize>(&self, node: &WideNode<W>) -> u32;
+}
+
+/// Wraps any test on [`Aabb3d`] volumes to test the children of a [`WideNode`] one at a time, see
+/// [`WideBvh::traverse`]
+#[derive(Clone, Debug)]
+pub struct PerChild<Test>(pub Test);
+
+impl<Test: IntersectsVolume<Aabb3d>> WideTest for PerChild<Test> {
+    #[inline(always)]
+    fn intersects_children<const W: usize>(&self, node: &WideNode<W>) -> u32 {
+        let mut mask = 0;
+        for child in 0..node.len as usize {
+            mask |= (self.0.intersects(&node.child_volume(child)) as u32) << child;
+        }
+        mask
+    }
+}
+
+impl<Volume: BoundingVolume, Test: IntersectsVolume<Volume>> IntersectsVolume<Volume>
+    for PerChild<Test>
+{
+    #[inline(always)]
+    fn intersects(&self, volume: &Volume) -> bool {
+        self.0.intersects(volume)
+    }
+}
+
+impl WideTest for Aabb3d {
+    #[inline(always)]
+    fn intersects_children<const W: usize>(&self, node: &WideNode<W>) -> u32 {
+        let mut mask = 0;
+        for child in 0..W {
+            let x = (self.min.x <= node.max_x[child]) & (node.min_x[child] <= self.max.x);
+            let y = (self.min.y <= node.max_y[child]) & (node.min_y[child] <= self.max.y);
+            let z = (self.min.z <= node.max_z[child]) & (node.min_z[child] <= self.max.z);
+            mask |= ((x & y & z) as u32) << child;
+        }
+        mask & node.used()
+    }
+}
+
+impl WideTest for RayCast3d {
+    #[inline(always)]
+    fn intersects_children<const W: usize>(&self, node: &WideNode<W>) -> u32 {
+        // Pick the near and far planes of each axis once for the whole node, like the slab test
+        // of the ray does for each box. NaNs from rays on a face are ignored by min/max
+        let planes = |positive: bool, min, max| match positive {
+            true => (min, max),
+            false => (max, min),
+        };
+        let positive = self.direction.signum().cmpgt(Vec3A::ZERO);
+        let (near_x, far_x) = planes(positive.test(0), &node.min_x, &node.max_x);
+        let (near_y, far_y) = planes(positive.test(1), &node.min_y, &node.max_y);
+        let (near_z, far_z) = planes(positive.test(2), &node.min_z, &node.max_z);
+        let origin = self.origin;
+        let recip = self.direction_recip();
+
+        let mut mask = 0;
+        for child in 0..W {
+            let entry = ((near_x[child] - origin.x) * recip.x)
+                .max((near_y[child] - origin.y) * recip.y)
+                .max((near_z[child] - origin.z) * recip.z)
+                .max(0.);
+            let exit = ((far_x[child] - origin.x) * recip.x)
+                .min((far_y[child] - origin.y) * recip.y)
+                .min((far_z[child] - origin.z) * recip.z)
+                .min(self.max);
+            mask |= ((entry <= exit) as u32) << child;
+        }
+        mask & node.used()
+    }
+}
+
+/// A BVH over [`Aabb3d`] volumes with up to `W` children per node, see [`WideBvh::new`]
+pub struct WideBvh<const W: usize, T, Item: BvhItemVolume<Aabb3d> = Aabb3d> {
+    nodes: Vec<WideNode<W>>,
+    items: Vec<BvhItem<Item, T>>,
+}
+
+impl<const W: usize, T, Item: BvhItemVolume<Aabb3d>> WideBvh<W, T, Item> {
+    /// Collapse a binary BVH into a wide one. Each node greedily pulls in the grandchildren of its
+    /// largest children until it has `W` children.
+    pub fn new(bvh: Bvh<Aabb3d, T, Item>) -> Self {
+        debug_assert!((2..=32).contains(&W));
+        let mut nodes = Vec::new();
+        if bvh.nodes.is_empty() {
+            return Self {
+                nodes,
+                items: bvh.items,
+            };
+        }
+
+        nodes.push(WideNode::EMPTY);
+        let mut queue = VecDeque::new();
+        queue.push_back((0, 0));
+        let mut children = Vec::with_capacity(W);
+        while let Some((binary, wide)) = queue.pop_front() {
+            let root = &bvh.nodes[binary];
+            children.clear();
+            if root.count > 0 {
+                children.push(binary);
+            } else {
+                let start = root.start_index as usize;
+                children.extend([start, start + 1]);
+            }
+
+            // Open the child with the largest area until the node is full
+            while children.len() < W {
+                let Some((index, _)) = children
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, child)| bvh.nodes[**child].count == 0)
+                    .max_by(|(_, a), (_, b)| {
+                        let a = bvh.nodes[**a].volume.visible_area();
+                        let b = bvh.nodes[**b].volume.visible_area();
+                        a.total_cmp(&b)
+                    })
+                else {
+                    break;
+                };
+                let start = bvh.nodes[children[index]].start_index as usize;
+                children.splice(index..=index, [start, start + 1]);
+            }
+
+            let mut node = WideNode::EMPTY;
+            node.len = children.len() as u32;
+            for (index, &binary) in children.iter().enumerate() {
+                let child = &bvh.nodes[binary];
+                if child.count > 0 {
+                    node.set_child(index, &child.volume, child.count, child.start_index);
+                    continue;
+                }
+
+                queue.push_back((binary, nodes.len()));
+                node.set_child(index, &child.volume, 0, nodes.len() as u32);
+                nodes.push(WideNode::EMPTY);
+            }
+            nodes[wide] = node;
+        }
+
+        Self {
+            nodes,
+            items: bvh.items,
+        }
+    }
+
+    /// Get the number of nodes in the BVH
+    pub fn n_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Get the number of items in the BVH
+    pub fn n_items(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Get an iterator over the BVH's nodes
+    pub fn nodes(&self) -> impl Iterator<Item = &WideNode<W>> {
+        self.nodes.iter()
+    }
+
+    /// Get an iterator over the BVH's items
+    pub fn items(&self) -> impl Iterator<Item = &BvhItem<Item, T>> {
+        self.items.iter()
+    }
+
+    /// Create a stack with the right size for the BVH
+    pub fn create_stack(&self) -> Stack {
+        let mut stack = Stack::default();
+        stack.reserve_exact(ceil_log2(self.items.len()) * (W - 1) + 10);
+        stack
+    }
+
+    /// Traverse the BVH with the provided test, which gets used on the children of each node one
+    /// at a time and on the item volumes
+    pub fn traverse<'a, Test: IntersectsVolume<Aabb3d> + IntersectsVolume<Item>>(
+        &'a self,
+        stack: &'a mut Stack,
+        tester: Test,
+    ) -> WideTraverser<'a, W, T, PerChild<Test>, Item> {
+        self.traverse_lanes(stack, PerChild(tester))
+    }
+
+    /// Traverse the BVH with the provided [`WideTest`], which tests all children of a node at once.
+    /// The test is also used on the item volumes
+    pub fn traverse_lanes<'a, Test: WideTest + IntersectsVolume<Item>>(
+        &'a self,
+        stack: &'a mut Stack,
+        tester: Test,
+    ) -> WideTraverser<'a, W, T, Test, Item> {
+        stack.clear();
+        if !self.nodes.is_empty() {
+            stack.push_front(0);
+        }
+
+        WideTraverser {
+            bvh: self,
+            tester,
+            stack,
+            node: 0,
+            mask: 0,
+            items: 0..0,
+        }
+    }
+}
+
+impl<const W: usize, T, Item: BvhItemVolume<Aabb3d>> From<Bvh<Aabb3d, T, Item>>
+    for WideBvh<W, T, Item>
+{
+    fn from(bvh: Bvh<Aabb3d, T, Item>) -> Self {
+        Self::new(bvh)
+    }
+}
+
+/// An iterator that traverses the [`WideBvh`] using the provided [`WideTest`]
+pub struct WideTraverser<
+    'a,
+    const W: usize,
+    T,
+    Test: WideTest + IntersectsVolume<Item>,
+    Item: BvhItemVolume<Aabb3d> = Aabb3d,
+> {
+    bvh: &'a WideBvh<W, T, Item>,
+    /// The test used in the traverser
+    pub tester: Test,
+    stack: &'a mut Stack,
+    node: u32,
+    mask: u32,
+    items: core::ops::Range<usize>,
+}
+
+impl<
+        'a,
+        const W: usize,
+        T,
+        Test: WideTest + IntersectsVolume<Item>,
+        Item: BvhItemVolume<Aabb3d>,
+    > Iterator for WideTraverser<'a, W, T, Test, Item>
+{
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            for index in self.items.by_ref() {
+                let item = &self.bvh.items[index];
+                if IntersectsVolume::<Item>::intersects(&self.tester, &item.volume) {
+                    return Some(&item.t);
+                }
+            }
+
+            if self.mask == 0 {
+                self.node = self.stack.pop_front()?;
+                self.mask = self.bvh.nodes[self.node as usize].intersects(&self.tester);
+                continue;
+            }
+
+            let child = self.mask.trailing_zeros() as usize;
+            self.mask &= self.mask - 1;
+            let node = &self.bvh.nodes[self.node as usize];
+            let start = node.start_index[child];
+            match node.count[child] {
+                0 => self.stack.push_front(start),
+                count => self.items = start as usize..(start + count) as usize,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+use {
+    crate::{dim3::BvhAabb3d, random_boxes, random_point},
+    bevy_math::{bounding::BoundingSphere, Dir3A},
+};
+
+#[test]
+fn test_wide() {
+    let boxes = random_boxes(2000, 200., 3.);
+    let build = || BvhAabb3d::new(boxes.len(), boxes.iter().copied().enumerate());
+    let bvh = build();
+    let bvh4 = Bvh4::new(build());
+    let bvh8 = Bvh8::new(build());
+    assert!(bvh8.n_nodes() < bvh4.n_nodes() && bvh4.n_nodes() < bvh.n_nodes());
+    let mut stack = bvh.create_stack();
+
+    let sorted = |hits: &mut dyn Iterator<Item = &usize>| {
+        let mut hits = hits.copied().collect::<Vec<_>>();
+        hits.sort();
+        hits
+    };
+    for _ in 0..50 {
+        let center = random_point(200.);
+        let query = Aabb3d::new(center, Vec3A::splat(10.));
+        let expected = sorted(&mut bvh.traverse(&mut stack, query));
+        assert_eq!(sorted(&mut bvh4.traverse(&mut stack, query)), expected);
+        assert_eq!(
+            sorted(&mut bvh4.traverse_lanes(&mut stack, query)),
+            expected
+        );
+        assert_eq!(
+            sorted(&mut bvh8.traverse_lanes(&mut stack, query)),
+            expected
+        );
+
+        let ray = RayCast3d::new(center, Dir3A::new(center - 100.).unwrap(), 150.);
+        let expected = sorted(&mut bvh.traverse(&mut stack, ray.clone()));
+        assert_eq!(
+            sorted(&mut bvh4.traverse(&mut stack, ray.clone())),
+            expected
+        );
+        assert_eq!(
+            sorted(&mut bvh4.traverse_lanes(&mut stack, ray.clone())),
+            expected
+        );
+        assert_eq!(sorted(&mut bvh8.traverse_lanes(&mut stack, ray)), expected);
+
+        // Tests without lanes run on one child at a time
+        let sphere = BoundingSphere::new(center, 10.);
+        let expected = sorted(&mut bvh.traverse(&mut stack, sphere));
+        assert_eq!(sorted(&mut bvh8.traverse(&mut stack, sphere)), expected);
+    }
+
+    // Rays starting exactly on the faces of a grid of cubes, without moving along that axis, touch
+    // the cubes on both sides of the face like bevy's test does
+    let grid = || {
+        let cubes = (0..64).map(|i| {
+            let min = Vec3A::new((i % 4) as f32, (i / 4 % 4) as f32, (i / 16) as f32);
+            (i, Aabb3d::new(min + 0.5, Vec3A::splat(0.5)))
+        });
+        BvhAabb3d::new(64, cubes)
+    };
+    let bvh = grid();
+    let bvh4 = Bvh4::new(grid());
+    for face in 0..=5 {
+        let offset = face as f32;
+        let rays = [
+            (Vec3A::new(-1., offset, 0.5), Dir3A::X),
+            (Vec3A::new(5., offset, 0.5), Dir3A::NEG_X),
+            (Vec3A::new(offset, -1., 0.5), Dir3A::Y),
+            (Vec3A::new(offset, 5., 0.5), Dir3A::NEG_Y),
+        ];
+        for (origin, direction) in rays {
+            let ray = RayCast3d::new(origin, direction, 10.);
+            let expected = sorted(&mut bvh.traverse(&mut stack, ray.clone()));
+            let rows = match face {
+                0 | 4 => 1,
+                5 => 0,
+                _ => 2,
+            };
+            assert_eq!(expected.len(), 4 * rows);
+            assert_eq!(sorted(&mut bvh4.traverse_lanes(&mut stack, ray)), expected);
+        }
+    }
+}